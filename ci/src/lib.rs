@@ -0,0 +1,93 @@
+use smol_vergen_core::{
+    output::{emit, Directive},
+    Plugin, SmolVergenContext, SmolVergenPluginItems, UnloadedPlugin,
+};
+mod provider;
+pub use provider::{CiInfo, CiProvider, CI_ENV_VARS};
+
+static DETECTED: &str = "DETECTED";
+static PROVIDER: &str = "PROVIDER";
+static RUN_ID: &str = "RUN_ID";
+static BUILD_NUMBER: &str = "BUILD_NUMBER";
+static JOB_URL: &str = "JOB_URL";
+static PR_NUMBER: &str = "PR_NUMBER";
+static ACTOR: &str = "ACTOR";
+
+/// Reports the CI system the build runs on
+///
+/// Emits `cargo:rerun-if-env-changed` for every variable in [CI_ENV_VARS].
+/// Like any `rerun-if` instruction this stops cargo from rerunning the build script on every file change.
+/// Cached runs emit the instructions again, so cargo keeps watching the variables.
+#[derive(Debug, Clone, Copy, Default)]
+#[non_exhaustive]
+pub struct CiPlugin;
+impl UnloadedPlugin for CiPlugin {
+    fn load(&self, _: std::path::PathBuf) -> anyhow::Result<Box<dyn Plugin>> {
+        Ok(Box::new(*self))
+    }
+}
+impl Plugin for CiPlugin {
+    fn id(&self) -> &'static str {
+        "CI"
+    }
+    fn run(&mut self, context: &mut SmolVergenContext) -> anyhow::Result<()> {
+        for var in CI_ENV_VARS {
+            emit(Directive::RerunIfEnvChanged(var.to_string()));
+        }
+        let info = CiInfo::detect(|var| std::env::var(var).ok());
+        add_info(context.get_plugin_items(self.id()), info);
+        Ok(())
+    }
+    /// The values of [CI_ENV_VARS]. The cache replays the `rerun-if-env-changed` instructions of [Plugin::run]
+    fn fingerprint(&self) -> Option<String> {
+        let mut fingerprint = "CI".to_owned();
+        for var in CI_ENV_VARS {
+            if let Ok(value) = std::env::var(var) {
+                fingerprint.push_str(&format!(";{}={}", var, value));
+            }
+        }
+        Some(fingerprint)
+    }
+}
+fn add_info(plugin_items: &mut SmolVergenPluginItems, info: Option<CiInfo>) {
+    plugin_items.add_item(DETECTED, info.is_some());
+    let Some(info) = info else {
+        return;
+    };
+    plugin_items.add_item(PROVIDER, info.provider.name());
+    plugin_items.add_optional_item(RUN_ID, info.run_id);
+    plugin_items.add_optional_item(BUILD_NUMBER, info.build_number);
+    plugin_items.add_optional_item(JOB_URL, info.job_url);
+    plugin_items.add_optional_item(PR_NUMBER, info.pr_number);
+    plugin_items.add_optional_item(ACTOR, info.actor);
+}
+
+#[cfg(test)]
+mod tests {
+    use smol_vergen::SmolVergenBuilder;
+
+    use crate::{CiPlugin, CI_ENV_VARS};
+
+    /// Cargo forgets the variables of a cached run unless the cache emits them again
+    #[test]
+    pub fn cached_run_keeps_rerun_instructions() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let cache_file = dir.path().join("cache.json");
+        let run = || {
+            SmolVergenBuilder {
+                directory: Some(dir.path().to_owned()),
+                ..Default::default()
+            }
+            .add_plugin(CiPlugin::default())
+            .cache_file(&cache_file)
+            .build()?
+            .run_capture()
+        };
+        let first = run()?;
+        assert!(cache_file.exists());
+        let second = run()?;
+        assert_eq!(second.rerun_if_env_changed(), CI_ENV_VARS);
+        assert_eq!(first, second);
+        Ok(())
+    }
+}
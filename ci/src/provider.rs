@@ -0,0 +1,315 @@
+use std::fmt::Display;
+
+/// A CI system detected from the environment
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CiProvider {
+    GitHubActions,
+    GitLab,
+    Jenkins,
+    Buildkite,
+    CircleCi,
+    AzurePipelines,
+    /// `CI` is set but the provider is unknown
+    Generic,
+}
+impl CiProvider {
+    /// The value of `SMOL_VERGEN_CI_PROVIDER`
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::GitHubActions => "github-actions",
+            Self::GitLab => "gitlab",
+            Self::Jenkins => "jenkins",
+            Self::Buildkite => "buildkite",
+            Self::CircleCi => "circleci",
+            Self::AzurePipelines => "azure-pipelines",
+            Self::Generic => "generic",
+        }
+    }
+}
+impl Display for CiProvider {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+/// Everything the CI plugin reports about the current build
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CiInfo {
+    pub provider: CiProvider,
+    /// The id of the pipeline, workflow or run
+    pub run_id: Option<String>,
+    /// The human readable build number
+    pub build_number: Option<String>,
+    /// A link to the build in the web interface of the provider
+    pub job_url: Option<String>,
+    /// The number of the pull or merge request being built
+    pub pr_number: Option<String>,
+    /// The user that triggered the build
+    pub actor: Option<String>,
+}
+/// Every environment variable read while detecting the CI system
+pub static CI_ENV_VARS: &[&str] = &[
+    "CI",
+    // GitHub Actions
+    "GITHUB_ACTIONS",
+    "GITHUB_RUN_ID",
+    "GITHUB_RUN_NUMBER",
+    "GITHUB_SERVER_URL",
+    "GITHUB_REPOSITORY",
+    "GITHUB_REF",
+    "GITHUB_ACTOR",
+    // GitLab CI
+    "GITLAB_CI",
+    "CI_PIPELINE_ID",
+    "CI_PIPELINE_IID",
+    "CI_JOB_URL",
+    "CI_MERGE_REQUEST_IID",
+    "GITLAB_USER_LOGIN",
+    // Jenkins
+    "JENKINS_URL",
+    "BUILD_ID",
+    "BUILD_NUMBER",
+    "BUILD_URL",
+    "CHANGE_ID",
+    "CHANGE_AUTHOR",
+    // Buildkite
+    "BUILDKITE",
+    "BUILDKITE_BUILD_ID",
+    "BUILDKITE_BUILD_NUMBER",
+    "BUILDKITE_BUILD_URL",
+    "BUILDKITE_PULL_REQUEST",
+    "BUILDKITE_BUILD_CREATOR",
+    // CircleCI
+    "CIRCLECI",
+    "CIRCLE_WORKFLOW_ID",
+    "CIRCLE_BUILD_NUM",
+    "CIRCLE_BUILD_URL",
+    "CIRCLE_PR_NUMBER",
+    "CIRCLE_PULL_REQUEST",
+    "CIRCLE_USERNAME",
+    // Azure Pipelines
+    "TF_BUILD",
+    "BUILD_BUILDID",
+    "BUILD_BUILDNUMBER",
+    "SYSTEM_COLLECTIONURI",
+    "SYSTEM_TEAMPROJECT",
+    "SYSTEM_PULLREQUEST_PULLREQUESTNUMBER",
+    "SYSTEM_PULLREQUEST_PULLREQUESTID",
+    "BUILD_REQUESTEDFOR",
+];
+impl CiInfo {
+    /// Detects the CI system from `env`. Returns `None` outside of CI
+    ///
+    /// Empty variables are treated as unset. `env` is only called with names in [CI_ENV_VARS]
+    pub fn detect(env: impl Fn(&str) -> Option<String>) -> Option<Self> {
+        let var = |name: &str| env(name).filter(|value| !value.is_empty());
+        let is_true = |name: &str| {
+            var(name).is_some_and(|value| matches!(value.to_lowercase().as_str(), "true" | "1"))
+        };
+        let info = if is_true("GITHUB_ACTIONS") {
+            let run_id = var("GITHUB_RUN_ID");
+            let job_url = match (var("GITHUB_SERVER_URL"), var("GITHUB_REPOSITORY"), &run_id) {
+                (Some(server), Some(repository), Some(run_id)) => Some(format!(
+                    "{}/{}/actions/runs/{}",
+                    server.trim_end_matches('/'),
+                    repository,
+                    run_id
+                )),
+                _ => None,
+            };
+            // Pull requests are checked out as `refs/pull/<number>/merge`
+            let pr_number = var("GITHUB_REF").and_then(|reference| {
+                reference
+                    .strip_prefix("refs/pull/")?
+                    .split_once('/')
+                    .map(|(number, _)| number.to_owned())
+            });
+            Self {
+                provider: CiProvider::GitHubActions,
+                run_id,
+                build_number: var("GITHUB_RUN_NUMBER"),
+                job_url,
+                pr_number,
+                actor: var("GITHUB_ACTOR"),
+            }
+        } else if is_true("GITLAB_CI") {
+            Self {
+                provider: CiProvider::GitLab,
+                run_id: var("CI_PIPELINE_ID"),
+                build_number: var("CI_PIPELINE_IID"),
+                job_url: var("CI_JOB_URL"),
+                pr_number: var("CI_MERGE_REQUEST_IID"),
+                actor: var("GITLAB_USER_LOGIN"),
+            }
+        } else if var("JENKINS_URL").is_some() {
+            Self {
+                provider: CiProvider::Jenkins,
+                run_id: var("BUILD_ID"),
+                build_number: var("BUILD_NUMBER"),
+                job_url: var("BUILD_URL"),
+                pr_number: var("CHANGE_ID"),
+                actor: var("CHANGE_AUTHOR"),
+            }
+        } else if is_true("BUILDKITE") {
+            Self {
+                provider: CiProvider::Buildkite,
+                run_id: var("BUILDKITE_BUILD_ID"),
+                build_number: var("BUILDKITE_BUILD_NUMBER"),
+                job_url: var("BUILDKITE_BUILD_URL"),
+                // `false` when the build is not for a pull request
+                pr_number: var("BUILDKITE_PULL_REQUEST").filter(|number| number != "false"),
+                actor: var("BUILDKITE_BUILD_CREATOR"),
+            }
+        } else if is_true("CIRCLECI") {
+            // `CIRCLE_PR_NUMBER` is only set for forks. Otherwise the number ends the pull request URL
+            let pr_number = var("CIRCLE_PR_NUMBER").or_else(|| {
+                var("CIRCLE_PULL_REQUEST")
+                    .and_then(|url| url.rsplit_once('/').map(|(_, number)| number.to_owned()))
+            });
+            Self {
+                provider: CiProvider::CircleCi,
+                run_id: var("CIRCLE_WORKFLOW_ID"),
+                build_number: var("CIRCLE_BUILD_NUM"),
+                job_url: var("CIRCLE_BUILD_URL"),
+                pr_number,
+                actor: var("CIRCLE_USERNAME"),
+            }
+        } else if is_true("TF_BUILD") {
+            let run_id = var("BUILD_BUILDID");
+            let job_url = match (
+                var("SYSTEM_COLLECTIONURI"),
+                var("SYSTEM_TEAMPROJECT"),
+                &run_id,
+            ) {
+                (Some(collection), Some(project), Some(run_id)) => Some(format!(
+                    "{}/{}/_build/results?buildId={}",
+                    collection.trim_end_matches('/'),
+                    project,
+                    run_id
+                )),
+                _ => None,
+            };
+            Self {
+                provider: CiProvider::AzurePipelines,
+                run_id,
+                build_number: var("BUILD_BUILDNUMBER"),
+                job_url,
+                pr_number: var("SYSTEM_PULLREQUEST_PULLREQUESTNUMBER")
+                    .or_else(|| var("SYSTEM_PULLREQUEST_PULLREQUESTID")),
+                actor: var("BUILD_REQUESTEDFOR"),
+            }
+        } else if is_true("CI") {
+            Self {
+                provider: CiProvider::Generic,
+                run_id: None,
+                build_number: None,
+                job_url: None,
+                pr_number: None,
+                actor: None,
+            }
+        } else {
+            return None;
+        };
+        Some(info)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::{CiInfo, CiProvider, CI_ENV_VARS};
+
+    fn detect(vars: &[(&str, &str)]) -> Option<CiInfo> {
+        let vars: HashMap<&str, &str> = vars.iter().copied().collect();
+        CiInfo::detect(|name| {
+            assert!(
+                CI_ENV_VARS.contains(&name),
+                "{} is not in CI_ENV_VARS",
+                name
+            );
+            vars.get(name).map(|value| value.to_string())
+        })
+    }
+    #[test]
+    pub fn test_not_ci() {
+        assert_eq!(detect(&[]), None);
+        assert_eq!(detect(&[("CI", "false")]), None);
+    }
+    #[test]
+    pub fn test_github_actions() {
+        let info = detect(&[
+            ("CI", "true"),
+            ("GITHUB_ACTIONS", "true"),
+            ("GITHUB_RUN_ID", "1658821493"),
+            ("GITHUB_RUN_NUMBER", "42"),
+            ("GITHUB_SERVER_URL", "https://github.com"),
+            ("GITHUB_REPOSITORY", "wyatt-herkamp/smol-vergen"),
+            ("GITHUB_REF", "refs/pull/12/merge"),
+            ("GITHUB_ACTOR", "octocat"),
+        ])
+        .unwrap();
+        assert_eq!(
+            info,
+            CiInfo {
+                provider: CiProvider::GitHubActions,
+                run_id: Some("1658821493".to_owned()),
+                build_number: Some("42".to_owned()),
+                job_url: Some(
+                    "https://github.com/wyatt-herkamp/smol-vergen/actions/runs/1658821493"
+                        .to_owned()
+                ),
+                pr_number: Some("12".to_owned()),
+                actor: Some("octocat".to_owned()),
+            }
+        );
+        let push = detect(&[
+            ("GITHUB_ACTIONS", "true"),
+            ("GITHUB_REF", "refs/heads/main"),
+        ]);
+        assert_eq!(push.unwrap().pr_number, None);
+    }
+    #[test]
+    pub fn test_buildkite_without_pull_request() {
+        let info = detect(&[
+            ("BUILDKITE", "true"),
+            ("BUILDKITE_BUILD_NUMBER", "7"),
+            ("BUILDKITE_PULL_REQUEST", "false"),
+        ])
+        .unwrap();
+        assert_eq!(info.provider, CiProvider::Buildkite);
+        assert_eq!(info.build_number.as_deref(), Some("7"));
+        assert_eq!(info.pr_number, None);
+    }
+    #[test]
+    pub fn test_circleci_pull_request_url() {
+        let info = detect(&[
+            ("CIRCLECI", "true"),
+            (
+                "CIRCLE_PULL_REQUEST",
+                "https://github.com/wyatt-herkamp/smol-vergen/pull/3",
+            ),
+        ])
+        .unwrap();
+        assert_eq!(info.pr_number.as_deref(), Some("3"));
+    }
+    #[test]
+    pub fn test_azure_job_url() {
+        let info = detect(&[
+            ("TF_BUILD", "True"),
+            ("BUILD_BUILDID", "99"),
+            ("SYSTEM_COLLECTIONURI", "https://dev.azure.com/org/"),
+            ("SYSTEM_TEAMPROJECT", "project"),
+        ])
+        .unwrap();
+        assert_eq!(
+            info.job_url.as_deref(),
+            Some("https://dev.azure.com/org/project/_build/results?buildId=99")
+        );
+    }
+    #[test]
+    pub fn test_generic() {
+        let info = detect(&[("CI", "1"), ("BUILD_NUMBER", "3")]).unwrap();
+        assert_eq!(info.provider, CiProvider::Generic);
+        assert_eq!(info.build_number, None);
+    }
+}
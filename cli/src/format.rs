@@ -0,0 +1,84 @@
+use std::{collections::BTreeMap, io::Write};
+
+use clap::ValueEnum;
+use smol_vergen::EnvFileFormat;
+
+/// How the values are printed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// Aligned names and values. Line breaks are shown as `\n`
+    #[default]
+    Table,
+    /// A JSON object of names and values
+    Json,
+    /// `NAME=value` lines for `.env` files
+    Env,
+    /// `export NAME='value'` lines for POSIX shells
+    Export,
+}
+impl OutputFormat {
+    pub fn write(
+        self,
+        values: &BTreeMap<String, String>,
+        out: &mut impl Write,
+    ) -> anyhow::Result<()> {
+        match self {
+            OutputFormat::Table => {
+                let width = values.keys().map(|key| key.len()).max().unwrap_or_default();
+                for (key, value) in values {
+                    let value = value.replace('\r', "\\r").replace('\n', "\\n");
+                    writeln!(out, "{:width$}  {}", key, value)?;
+                }
+            }
+            OutputFormat::Json => {
+                serde_json::to_writer_pretty(&mut *out, values)?;
+                writeln!(out)?;
+            }
+            OutputFormat::Env => EnvFileFormat::Dotenv.write(values, out)?,
+            OutputFormat::Export => EnvFileFormat::ShellExport.write(values, out)?,
+        }
+        Ok(())
+    }
+}
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::OutputFormat;
+
+    fn write(format: OutputFormat) -> String {
+        let values: BTreeMap<String, String> = [
+            ("SMOL_VERGEN_GIT_BRANCH", "main"),
+            ("SMOL_VERGEN_GIT_COMMIT_BODY", "It's \"$HOME\"\nC:\\"),
+        ]
+        .into_iter()
+        .map(|(key, value)| (key.to_owned(), value.to_owned()))
+        .collect();
+        let mut out = Vec::new();
+        format.write(&values, &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+    #[test]
+    pub fn test_formats() {
+        assert_eq!(
+            write(OutputFormat::Table),
+            "SMOL_VERGEN_GIT_BRANCH       main\n\
+             SMOL_VERGEN_GIT_COMMIT_BODY  It's \"$HOME\"\\nC:\\\n"
+        );
+        assert_eq!(
+            write(OutputFormat::Env),
+            "# Generated by smol-vergen\n\
+             SMOL_VERGEN_GIT_BRANCH=main\n\
+             SMOL_VERGEN_GIT_COMMIT_BODY=\"It's \\\"\\$HOME\\\"\\nC:\\\\\"\n"
+        );
+        assert_eq!(
+            write(OutputFormat::Export),
+            "# Generated by smol-vergen\n\
+             export SMOL_VERGEN_GIT_BRANCH='main'\n\
+             export SMOL_VERGEN_GIT_COMMIT_BODY='It'\\''s \"$HOME\"\nC:\\'\n"
+        );
+        let json: BTreeMap<String, String> =
+            serde_json::from_str(&write(OutputFormat::Json)).unwrap();
+        assert_eq!(json["SMOL_VERGEN_GIT_BRANCH"], "main");
+    }
+}
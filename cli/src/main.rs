@@ -0,0 +1,127 @@
+//! Prints the values a build using smol-vergen would embed, without running `cargo build`
+use std::path::PathBuf;
+
+use clap::{Parser, ValueEnum};
+use format::OutputFormat;
+use smol_vergen::{EnvFileError, SmolVergenBuilder};
+use smol_vergen_ci::CiPlugin;
+use smol_vergen_git::{GitBackend, GitPluginBuilder};
+mod format;
+
+/// A plugin that can be selected with `--plugin`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum PluginName {
+    Git,
+    Ci,
+}
+#[derive(Debug, Parser)]
+#[command(version, about)]
+struct Args {
+    /// The directory to run the plugins in. The current directory by default
+    directory: Option<PathBuf>,
+    #[arg(short, long, value_enum, default_value_t)]
+    format: OutputFormat,
+    /// The prefix of every variable
+    #[arg(long, default_value = smol_vergen::DEFAULT_PREFIX)]
+    prefix: String,
+    /// The git backend: gix, git2 or cli. Only backends enabled as features are available
+    #[arg(long)]
+    backend: Option<GitBackend>,
+    /// The plugins to run. All by default
+    #[arg(short, long = "plugin", value_enum, value_delimiter = ',')]
+    plugins: Vec<PluginName>,
+    /// Print the log lines of the plugins to stderr
+    #[arg(short, long)]
+    verbose: bool,
+}
+impl Args {
+    fn builder(&self) -> anyhow::Result<SmolVergenBuilder> {
+        let directory = match &self.directory {
+            Some(directory) => directory.clone(),
+            None => std::env::current_dir()?,
+        };
+        let mut builder = SmolVergenBuilder {
+            directory: Some(directory),
+            ..Default::default()
+        }
+        .prefix(&self.prefix);
+        let all = self.plugins.is_empty();
+        if all || self.plugins.contains(&PluginName::Git) {
+            let mut git = GitPluginBuilder::default();
+            git.check_parents(true);
+            if let Some(backend) = self.backend {
+                git.builtin_backend(backend);
+            }
+            builder = builder.add_plugin(git.build());
+        }
+        if all || self.plugins.contains(&PluginName::Ci) {
+            builder = builder.add_plugin(CiPlugin::default());
+        }
+        Ok(builder)
+    }
+}
+
+fn main() -> anyhow::Result<()> {
+    let args = Args::parse();
+    let output = smol_vergen::capture::run(args.builder()?)?;
+    for warning in output.warnings() {
+        eprintln!("warning: {}", warning);
+    }
+    if args.verbose {
+        for line in output.logs() {
+            eprintln!("{}", line);
+        }
+    }
+    match args
+        .format
+        .write(&output.env(), &mut std::io::stdout().lock())
+    {
+        // The reader, such as `head`, is done
+        Err(err) if is_broken_pipe(&err) => Ok(()),
+        result => result,
+    }
+}
+fn is_broken_pipe(err: &anyhow::Error) -> bool {
+    let io = match err.downcast_ref::<EnvFileError>() {
+        Some(EnvFileError::Io(err)) => Some(err),
+        _ => err.downcast_ref::<std::io::Error>(),
+    };
+    io.is_some_and(|err| err.kind() == std::io::ErrorKind::BrokenPipe)
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+    use smol_vergen_git::GitBackend;
+
+    use crate::{format::OutputFormat, Args, PluginName};
+
+    #[test]
+    pub fn parse_args() {
+        let args = Args::try_parse_from([
+            "smol-vergen",
+            "--format",
+            "export",
+            "--backend",
+            "git2",
+            "--plugin",
+            "git,ci",
+            "--prefix",
+            "APP",
+            "some/crate",
+        ])
+        .unwrap();
+        assert_eq!(args.format, OutputFormat::Export);
+        assert_eq!(args.backend, Some(GitBackend::Git2));
+        assert_eq!(args.plugins, vec![PluginName::Git, PluginName::Ci]);
+        assert_eq!(args.prefix, "APP");
+        assert!(Args::try_parse_from(["smol-vergen", "--backend", "svn"]).is_err());
+    }
+    #[test]
+    pub fn run_ci_plugin() -> anyhow::Result<()> {
+        let args = Args::try_parse_from(["smol-vergen", "--plugin", "ci", "--prefix", "APP"])?;
+        let output = smol_vergen::capture::run(args.builder()?)?;
+        assert!(output.env().keys().all(|key| key.starts_with("APP_CI_")));
+        Ok(())
+    }
+}
@@ -1,5 +1,5 @@
 #[macro_export]
 macro_rules! warn {
     // warn!("a {} event", "log")
-    ($($arg:tt)+) => (println!("cargo:warning={}",format_args!($($arg)+)))
+    ($($arg:tt)+) => ($crate::output::emit($crate::output::Directive::Warning(format!($($arg)+))))
 }
@@ -1,21 +1,58 @@
 use std::{fmt::Debug, path::PathBuf};
 mod value;
-use ahash::HashMap;
+use ahash::{HashMap, HashMapExt};
 use erased_serde::Serialize;
-use value::Value;
+use serialize_to_env::{serialize_to_map, SerializeToEnvError};
+use thiserror::Error;
+pub use value::Value;
+mod timestamp;
+pub use timestamp::{InvalidStrftimeError, StrftimePattern, TimestampFormat, TimestampPolicy};
 mod cargo_logger;
+pub mod output;
+pub mod serialize_to_env;
 
-pub trait Plugin {
+/// A loaded plugin
+///
+/// Plugins without [Plugin::dependencies] run concurrently, each on its own context.
+pub trait Plugin: Send {
+    /// The id other plugins list in their [Plugin::dependencies]. The name the plugin adds its items under, such as `GIT`
+    fn id(&self) -> &'static str;
     fn run(&mut self, context: &mut SmolVergenContext) -> anyhow::Result<()>;
+    /// The ids of the plugins whose items this plugin reads from the context
+    ///
+    /// Plugins with dependencies run one after another once all other plugins are done
+    /// and receive the shared context. A plugin runs after every plugin it depends on.
+    /// Unknown ids and cycles fail the run. Their output is never cached.
+    fn dependencies(&self) -> &[&'static str] {
+        &[]
+    }
+    /// A fingerprint of everything the output of the plugin depends on
+    ///
+    /// If caching is enabled and the fingerprint matches a cached run, the cached items are used instead of running the plugin.
+    /// The directives the plugin emitted through [output::emit] during that run, such as `cargo:rerun-if-env-changed`, are emitted again.
+    /// Returning `None` disables caching for the plugin.
+    fn fingerprint(&self) -> Option<String> {
+        None
+    }
 }
 pub trait UnloadedPlugin {
     fn load(&self, directory: PathBuf) -> anyhow::Result<Box<dyn Plugin>>;
 }
 
+/// A complex item that could not be flattened into environment variables
+#[derive(Debug, Error)]
+#[error("Failed to serialize complex item {key} of plugin {plugin}: {source}")]
+pub struct ComplexItemError {
+    pub plugin: String,
+    pub key: String,
+    pub source: SerializeToEnvError,
+}
 #[derive(Default)]
 pub struct SmolVergenPluginItems {
     pub items: HashMap<String, Value>,
-    pub complex_items: HashMap<String, Box<dyn Serialize>>,
+    pub complex_items: HashMap<String, Box<dyn Serialize + Send>>,
+    plugin: String,
+    validate_complex_items: bool,
 }
 impl Debug for SmolVergenPluginItems {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -36,38 +73,140 @@ impl SmolVergenPluginItems {
     }
 
     /// Add a new complex item to the plugin
-    pub fn add_complex_item<V: Serialize + 'static>(&mut self, name: impl Into<String>, item: V) {
-        let item_boxed: Box<dyn Serialize> = Box::new(item);
-        self.complex_items.insert(name.into(), item_boxed);
+    ///
+    /// If complex item validation is enabled the item is serialized right away
+    /// and an error is returned if it can not be flattened into environment variables.
+    pub fn add_complex_item<V: Serialize + Send + 'static>(
+        &mut self,
+        name: impl Into<String>,
+        item: V,
+    ) -> Result<(), ComplexItemError> {
+        let name = name.into();
+        let item_boxed: Box<dyn Serialize + Send> = Box::new(item);
+        if self.validate_complex_items {
+            serialize_to_map(name.as_str(), item_boxed.as_ref())
+                .map_err(|err| self.complex_item_error(&name, err))?;
+        }
+        self.complex_items.insert(name, item_boxed);
+        Ok(())
     }
-    pub fn add_optional_complex_item<V: Serialize + 'static>(
+    pub fn add_optional_complex_item<V: Serialize + Send + 'static>(
         &mut self,
         name: impl Into<String>,
         item: Option<V>,
-    ) {
+    ) -> Result<(), ComplexItemError> {
         if let Some(item) = item {
-            self.add_complex_item(name, item);
+            self.add_complex_item(name, item)?;
+        }
+        Ok(())
+    }
+    /// Flattens all complex items into environment variables prefixed with `base_name`
+    pub fn complex_items_to_map(
+        &self,
+        base_name: &str,
+    ) -> Result<HashMap<String, String>, ComplexItemError> {
+        let mut result = HashMap::new();
+        for key in self.complex_items.keys() {
+            result.extend(self.complex_item_to_map(base_name, key)?);
+        }
+        Ok(result)
+    }
+    /// Flattens the complex item `key` into environment variables prefixed with `base_name`
+    pub fn complex_item_to_map(
+        &self,
+        base_name: &str,
+        key: &str,
+    ) -> Result<HashMap<String, String>, ComplexItemError> {
+        let Some(value) = self.complex_items.get(key) else {
+            return Ok(HashMap::new());
+        };
+        let prefix = format!("{}_{}", base_name, key);
+        serialize_to_map(prefix, value.as_ref()).map_err(|err| self.complex_item_error(key, err))
+    }
+    fn complex_item_error(&self, key: &str, source: SerializeToEnvError) -> ComplexItemError {
+        ComplexItemError {
+            plugin: self.plugin.clone(),
+            key: key.to_owned(),
+            source,
         }
     }
 }
 /// Adds a key-pair to the Rustc environment
 /// This is a helper function to add a key-pair to the Rustc environment
+///
+/// `cargo:rustc-env` can not span multiple lines so the value is written with [output::escape_env_value]
 #[doc(hidden)]
 pub fn add_to_env(key: &str, value: &str) {
-    println!("cargo:rustc-env={}={}", key, value);
+    output::emit(output::Directive::RustcEnv {
+        key: key.to_owned(),
+        value: value.to_owned(),
+    });
 }
 #[derive(Default)]
 pub struct SmolVergenContext {
-    items: HashMap<&'static str, SmolVergenPluginItems>,
+    items: HashMap<String, SmolVergenPluginItems>,
+    validate_complex_items: bool,
 }
 impl SmolVergenContext {
+    /// Creates a new context
+    ///
+    /// If `validate_complex_items` is true complex items are serialized when they are added.
+    pub fn new(validate_complex_items: bool) -> Self {
+        Self {
+            items: HashMap::new(),
+            validate_complex_items,
+        }
+    }
+    /// Creates an empty context with the same settings as this one
+    pub fn fork(&self) -> Self {
+        Self::new(self.validate_complex_items)
+    }
     /// Get the plugin items for a given plugin
-    pub fn get_plugin_items(&mut self, plugin_name: &'static str) -> &mut SmolVergenPluginItems {
+    pub fn get_plugin_items(&mut self, plugin_name: &str) -> &mut SmolVergenPluginItems {
+        let validate_complex_items = self.validate_complex_items;
         self.items
-            .entry(plugin_name)
-            .or_insert_with(SmolVergenPluginItems::default)
+            .entry(plugin_name.to_owned())
+            .or_insert_with(|| SmolVergenPluginItems {
+                plugin: plugin_name.to_owned(),
+                validate_complex_items,
+                ..Default::default()
+            })
+    }
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &SmolVergenPluginItems)> {
+        self.items.iter().map(|(k, v)| (k.as_str(), v))
+    }
+    /// Moves all items of `other` into this context
+    ///
+    /// Items of `other` replace items with the same key.
+    pub fn merge(&mut self, other: SmolVergenContext) {
+        for (plugin_name, other_items) in other.items {
+            let items = self.get_plugin_items(&plugin_name);
+            items.items.extend(other_items.items);
+            items.complex_items.extend(other_items.complex_items);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{serialize_to_env::SerializeToEnvError, SmolVergenContext};
+
+    #[test]
+    pub fn complex_item_error_names_plugin_and_key() {
+        let mut context = SmolVergenContext::default();
+        let items = context.get_plugin_items("TEST");
+        items.add_complex_item("LIST", vec![1, 2, 3]).unwrap();
+        let err = items.complex_items_to_map("SMOL_VERGEN_TEST").unwrap_err();
+        assert_eq!(err.plugin, "TEST");
+        assert_eq!(err.key, "LIST");
+        assert!(matches!(err.source, SerializeToEnvError::Custom(_)));
     }
-    pub fn iter(&self) -> impl Iterator<Item = (&'static str, &SmolVergenPluginItems)> {
-        self.items.iter().map(|(k, v)| (*k, v))
+    #[test]
+    pub fn validate_complex_items_on_add() {
+        let mut context = SmolVergenContext::new(true);
+        let items = context.get_plugin_items("TEST");
+        let err = items.add_complex_item("LIST", vec![1, 2, 3]).unwrap_err();
+        assert_eq!(err.key, "LIST");
+        assert!(items.complex_items.is_empty());
     }
 }
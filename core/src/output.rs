@@ -0,0 +1,158 @@
+use std::{
+    cell::RefCell,
+    fmt::Display,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+};
+
+use serde::{Deserialize, Serialize};
+
+/// A line written to the output of the build script
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Directive {
+    /// `cargo:rustc-env=KEY=VALUE`. The value is stored unescaped and written with [escape_env_value]
+    RustcEnv { key: String, value: String },
+    /// `cargo:warning=MESSAGE`
+    Warning(String),
+    /// `cargo:rerun-if-changed=PATH`
+    RerunIfChanged(PathBuf),
+    /// `cargo:rerun-if-env-changed=VAR`
+    RerunIfEnvChanged(String),
+    /// A line that is not an instruction. Cargo only keeps it in the output file of the build script
+    Log(String),
+}
+impl Display for Directive {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Directive::RustcEnv { key, value } => {
+                write!(f, "cargo:rustc-env={}={}", key, escape_env_value(value))
+            }
+            Directive::Warning(message) => write!(f, "cargo:warning={}", message),
+            Directive::RerunIfChanged(path) => {
+                write!(f, "cargo:rerun-if-changed={}", path.display())
+            }
+            Directive::RerunIfEnvChanged(var) => write!(f, "cargo:rerun-if-env-changed={}", var),
+            Directive::Log(line) => f.write_str(line),
+        }
+    }
+}
+/// Escapes a value for `cargo:rustc-env`, which can not span multiple lines
+///
+/// `\` is written as `\\`, a line feed as `\n` and a carriage return as `\r`. Everything else is kept.
+/// [unescape_env_value] restores the value read with `env!`.
+pub fn escape_env_value(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+/// Reverses [escape_env_value]. Unknown escapes are kept as they are
+pub fn unescape_env_value(value: &str) -> String {
+    let mut unescaped = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('\\') => unescaped.push('\\'),
+            Some('n') => unescaped.push('\n'),
+            Some('r') => unescaped.push('\r'),
+            Some(other) => {
+                unescaped.push('\\');
+                unescaped.push(other);
+            }
+            None => unescaped.push('\\'),
+        }
+    }
+    unescaped
+}
+thread_local! {
+    static SINK: RefCell<Option<OutputSink>> = const { RefCell::new(None) };
+}
+/// Collects the directives emitted on every thread it is installed on
+#[derive(Debug, Clone, Default)]
+pub struct OutputSink(Arc<Mutex<Vec<Directive>>>);
+impl OutputSink {
+    /// The sink installed on the current thread
+    ///
+    /// Pass it to threads spawned while capturing and [OutputSink::install] it there.
+    pub fn current() -> Option<Self> {
+        SINK.with(|sink| sink.borrow().clone())
+    }
+    /// Captures the directives of the current thread until the guard is dropped
+    pub fn install(&self) -> SinkGuard {
+        let previous = SINK.with(|sink| sink.replace(Some(self.clone())));
+        SinkGuard { previous }
+    }
+    /// The directives emitted so far
+    pub fn directives(&self) -> Vec<Directive> {
+        self.0
+            .lock()
+            .map(|directives| directives.clone())
+            .unwrap_or_else(|poisoned| poisoned.into_inner().clone())
+    }
+    fn push(&self, directive: Directive) {
+        match self.0.lock() {
+            Ok(mut directives) => directives.push(directive),
+            Err(poisoned) => poisoned.into_inner().push(directive),
+        }
+    }
+}
+/// Restores the previous sink of the thread when dropped
+#[must_use]
+pub struct SinkGuard {
+    previous: Option<OutputSink>,
+}
+impl Drop for SinkGuard {
+    fn drop(&mut self) {
+        SINK.with(|sink| *sink.borrow_mut() = self.previous.take());
+    }
+}
+/// Prints the directive or records it if an [OutputSink] is installed on this thread
+pub fn emit(directive: Directive) {
+    match OutputSink::current() {
+        Some(sink) => sink.push(directive),
+        None => println!("{}", directive),
+    }
+}
+/// Runs `f` and returns the directives it emitted instead of printing them
+pub fn capture<T>(f: impl FnOnce() -> T) -> (T, Vec<Directive>) {
+    let sink = OutputSink::default();
+    let result = {
+        let _guard = sink.install();
+        f()
+    };
+    (result, sink.directives())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{capture, emit, escape_env_value, unescape_env_value, Directive};
+
+    #[test]
+    pub fn capture_nested() {
+        let ((_, inner), outer) = capture(|| {
+            emit(Directive::Warning("outer".to_owned()));
+            capture(|| crate::add_to_env("KEY", "a\nb"))
+        });
+        assert_eq!(outer, vec![Directive::Warning("outer".to_owned())]);
+        assert_eq!(inner[0].to_string(), "cargo:rustc-env=KEY=a\\nb");
+    }
+    #[test]
+    pub fn escape_round_trip() {
+        let value = "C:\\new\nline\r\n\\n";
+        let escaped = escape_env_value(value);
+        assert_eq!(escaped, "C:\\\\new\\nline\\r\\n\\\\n");
+        assert!(!escaped.contains(['\n', '\r']));
+        assert_eq!(unescape_env_value(&escaped), value);
+        assert_eq!(unescape_env_value("\\t\\"), "\\t\\");
+    }
+}
@@ -13,8 +13,7 @@ use self::{
     ser_struct::SerializeToEnvStruct,
 };
 /// Serialize a value to a map of environment variables
-#[allow(unused)]
-pub(crate) fn serialize_to_map<T: Serialize>(
+pub fn serialize_to_map<T: Serialize + ?Sized>(
     prefix: impl Into<String>,
     serialize: &T,
 ) -> Result<HashMap<String, String>, SerializeToEnvError> {
@@ -94,9 +93,9 @@ impl<'ser> Serializer for &'ser mut SerializeToEnv<'ser> {
         fn serialize_bytes(self,  &[u8])
     );
 
-    fn serialize_some<T: ?Sized>(self, _value: &T) -> Result<Self::Ok, Self::Error>
+    fn serialize_some<T>(self, _value: &T) -> Result<Self::Ok, Self::Error>
     where
-        T: serde::Serialize,
+        T: ?Sized + serde::Serialize,
     {
         Err(SerializeToEnvError::Custom(
             "Cannot serialize Option<T> to environment variable".to_owned(),
@@ -108,7 +107,7 @@ impl<'ser> Serializer for &'ser mut SerializeToEnv<'ser> {
         Ok(SerializeToEnvMap {
             prefix: self.prefix.clone(),
             key: None,
-            result: &mut self.result,
+            result: self.result,
         })
     }
 
@@ -120,7 +119,7 @@ impl<'ser> Serializer for &'ser mut SerializeToEnv<'ser> {
         Ok(SerializeToEnvStruct {
             prefix: self.prefix.clone(),
             key: None,
-            result: &mut self.result,
+            result: self.result,
         })
     }
 
@@ -139,3 +139,30 @@ macro_rules! not_possible {
 }
 
 pub(crate) use not_possible;
+/// Serializes maps and structs inside of complex types by using the current key as their prefix
+macro_rules! nested_complex {
+    () => {
+        fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, Self::Error> {
+            let prefix = self.key.take().ok_or(SerializeToEnvError::MissingKey)?;
+            Ok(SerializeToEnvMap {
+                prefix,
+                key: None,
+                result: &mut *self.result,
+            })
+        }
+
+        fn serialize_struct(
+            self,
+            _name: &'static str,
+            _len: usize,
+        ) -> Result<Self::SerializeStruct, Self::Error> {
+            let prefix = self.key.take().ok_or(SerializeToEnvError::MissingKey)?;
+            Ok(SerializeToEnvStruct {
+                prefix,
+                key: None,
+                result: &mut *self.result,
+            })
+        }
+    };
+}
+pub(crate) use nested_complex;
@@ -1,5 +1,5 @@
 use super::{
-    macros::{not_possible, simple_not_possible, simple_serialize_field},
+    macros::{nested_complex, not_possible, simple_not_possible, simple_serialize_field},
     ser_map_key::KeyToStringSerializer,
     ser_struct::SerializeToEnvStruct,
     SerializeToEnvError,
@@ -23,9 +23,9 @@ impl SerializeMap for SerializeToEnvMap<'_> {
         Ok(())
     }
 
-    fn serialize_key<T: ?Sized>(&mut self, key: &T) -> Result<(), Self::Error>
+    fn serialize_key<T>(&mut self, key: &T) -> Result<(), Self::Error>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
         let must_be_string = KeyToStringSerializer {
             prefix: &self.prefix,
@@ -34,17 +34,14 @@ impl SerializeMap for SerializeToEnvMap<'_> {
         key.serialize(must_be_string)
     }
 
-    fn serialize_value<T: ?Sized>(&mut self, value: &T) -> Result<(), Self::Error>
+    fn serialize_value<T>(&mut self, value: &T) -> Result<(), Self::Error>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
-        if self.key.is_none() {
-            return Err(SerializeToEnvError::MissingKey);
-        }
-        let key = self.key.as_ref().unwrap();
+        let key = self.key.take().ok_or(SerializeToEnvError::MissingKey)?;
         let mut ser = SerializeToEnvStruct {
             prefix: key.clone(),
-            key: None,
+            key: Some(key),
             result: self.result,
         };
         value.serialize(&mut ser)
@@ -79,18 +76,11 @@ impl<'ser> Serializer for &'ser mut SerializeToEnvMap<'_> {
         Ok(())
     }
 
-    fn serialize_some<T: ?Sized>(self, value: &T) -> Result<Self::Ok, Self::Error>
+    fn serialize_some<T>(self, value: &T) -> Result<Self::Ok, Self::Error>
     where
-        T: serde::Serialize,
+        T: ?Sized + serde::Serialize,
     {
         value.serialize(self)
     }
-    not_possible! {
-        function: fn serialize_map(self, _size: Option<usize>) -> Result<Self::SerializeMap, Self::Error>,
-        error: "Complex Types in Complex Types are not supported"
-    }
-    not_possible! {
-        function:  fn serialize_struct(self, _name: &'static str,_len: usize) -> Result<Self::SerializeStruct, Self::Error>,
-        error: "Complex Types in Complex Types are not supported"
-    }
+    nested_complex!();
 }
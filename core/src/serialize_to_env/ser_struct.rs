@@ -6,7 +6,7 @@ use serde::{
 };
 
 use super::{
-    macros::{not_possible, simple_not_possible, simple_serialize_field},
+    macros::{nested_complex, not_possible, simple_not_possible, simple_serialize_field},
     ser_map::SerializeToEnvMap,
     SerializeToEnvError,
 };
@@ -21,13 +21,9 @@ impl SerializeStruct for SerializeToEnvStruct<'_> {
 
     type Error = SerializeToEnvError;
 
-    fn serialize_field<T: ?Sized>(
-        &mut self,
-        key: &'static str,
-        value: &T,
-    ) -> Result<(), Self::Error>
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<(), Self::Error>
     where
-        T: serde::Serialize,
+        T: ?Sized + serde::Serialize,
     {
         let key = format!("{}_{}", self.prefix, key.to_shouty_snake_case());
         if self.key.is_some() {
@@ -70,19 +66,12 @@ impl<'ser> Serializer for &'ser mut SerializeToEnvStruct<'_> {
         Ok(())
     }
 
-    fn serialize_some<T: ?Sized>(self, value: &T) -> Result<Self::Ok, Self::Error>
+    fn serialize_some<T>(self, value: &T) -> Result<Self::Ok, Self::Error>
     where
-        T: serde::Serialize,
+        T: ?Sized + serde::Serialize,
     {
         value.serialize(self)
     }
 
-    not_possible! {
-        function: fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, Self::Error>,
-        error: "Complex Types in Complex Types are not supported"
-    }
-    not_possible! {
-        function:  fn serialize_struct(self, _name: &'static str,_len: usize) -> Result<Self::SerializeStruct, Self::Error>,
-        error: "Complex Types in Complex Types are not supported"
-    }
+    nested_complex!();
 }
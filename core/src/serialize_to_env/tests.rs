@@ -0,0 +1,54 @@
+use serde::Serialize;
+
+use super::serialize_to_map;
+
+#[derive(Serialize)]
+pub struct BasicSerializeStruct {
+    name: String,
+    email: String,
+}
+#[test]
+pub fn test() {
+    let s = BasicSerializeStruct {
+        name: "John Doe".to_owned(),
+        email: "test@example.com".to_owned(),
+    };
+    let result = serialize_to_map("TEST", &s).unwrap();
+    assert_eq!(result.get("TEST_NAME").unwrap(), "John Doe");
+    assert_eq!(result.get("TEST_EMAIL").unwrap(), "test@example.com");
+}
+#[test]
+pub fn test_map() {
+    let mut map = std::collections::BTreeMap::new();
+    map.insert("name", "John Doe");
+    map.insert("email", "test@example.com");
+    let result = serialize_to_map("TEST", &map).unwrap();
+    assert_eq!(result.get("TEST_NAME").unwrap(), "John Doe");
+    assert_eq!(result.get("TEST_EMAIL").unwrap(), "test@example.com");
+}
+#[derive(Serialize)]
+pub struct NestedSerializeStruct {
+    author: BasicSerializeStruct,
+}
+#[test]
+pub fn test_nested() {
+    let mut map = std::collections::BTreeMap::new();
+    map.insert(
+        "vendor/lib",
+        NestedSerializeStruct {
+            author: BasicSerializeStruct {
+                name: "John Doe".to_owned(),
+                email: "test@example.com".to_owned(),
+            },
+        },
+    );
+    let result = serialize_to_map("TEST", &map).unwrap();
+    assert_eq!(
+        result.get("TEST_VENDOR_LIB_AUTHOR_NAME").unwrap(),
+        "John Doe"
+    );
+    assert_eq!(
+        result.get("TEST_VENDOR_LIB_AUTHOR_EMAIL").unwrap(),
+        "test@example.com"
+    );
+}
@@ -0,0 +1,177 @@
+use std::fmt::Write;
+
+use chrono::{
+    format::{Item, StrftimeItems},
+    DateTime, FixedOffset,
+};
+use thiserror::Error;
+
+/// How a timestamp is written to the environment
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum TimestampFormat {
+    /// RFC 3339. `2024-05-01T12:30:00+02:00`
+    #[default]
+    Rfc3339,
+    /// Seconds since the Unix epoch. `1714559400`
+    Unix,
+    /// The calendar date. `2024-05-01`
+    Date,
+    /// A custom pattern created with [TimestampFormat::strftime]
+    Strftime(StrftimePattern),
+}
+/// A validated `strftime` pattern
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StrftimePattern(String);
+impl StrftimePattern {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+#[derive(Debug, Error)]
+#[error("Invalid strftime pattern {0:?}")]
+pub struct InvalidStrftimeError(pub String);
+
+impl TimestampFormat {
+    /// A custom `strftime` pattern such as `%Y%m%d`
+    ///
+    /// The pattern is checked here because chrono panics on invalid patterns when formatting.
+    pub fn strftime(pattern: impl Into<String>) -> Result<Self, InvalidStrftimeError> {
+        let pattern = pattern.into();
+        if StrftimeItems::new(&pattern).any(|item| matches!(item, Item::Error)) {
+            return Err(InvalidStrftimeError(pattern));
+        }
+        Ok(Self::Strftime(StrftimePattern(pattern)))
+    }
+    pub fn format(&self, timestamp: &DateTime<FixedOffset>) -> String {
+        match self {
+            TimestampFormat::Rfc3339 => timestamp.to_rfc3339(),
+            TimestampFormat::Unix => timestamp.timestamp().to_string(),
+            TimestampFormat::Date => timestamp.date_naive().to_string(),
+            TimestampFormat::Strftime(pattern) => {
+                let mut result = String::new();
+                // Patterns are validated so writing can only fail for values the pattern can not represent
+                let _ = write!(result, "{}", timestamp.format(pattern.as_str()));
+                result
+            }
+        }
+    }
+}
+/// The environment variable a representation is written to
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum RepresentationKey {
+    /// The key of the item followed by the suffix
+    Suffix(String),
+    /// The key of the item with a trailing `TIMESTAMP` replaced by the name
+    Rename(String),
+}
+impl RepresentationKey {
+    fn key(&self, key: &str) -> String {
+        match self {
+            RepresentationKey::Suffix(suffix) => format!("{}{}", key, suffix),
+            RepresentationKey::Rename(name) => match key.strip_suffix("TIMESTAMP") {
+                Some(base) => format!("{}{}", base, name),
+                None => format!("{}_{}", key, name),
+            },
+        }
+    }
+}
+/// How timestamps are written to the environment and under which keys
+///
+/// The default writes each timestamp once as RFC 3339 in the offset it was recorded in.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TimestampPolicy {
+    representations: Vec<(RepresentationKey, TimestampFormat)>,
+    utc: bool,
+}
+impl Default for TimestampPolicy {
+    fn default() -> Self {
+        Self::new(TimestampFormat::Rfc3339)
+    }
+}
+impl TimestampPolicy {
+    /// Writes timestamps under the key of the item in `format`
+    pub fn new(format: TimestampFormat) -> Self {
+        Self {
+            representations: vec![(RepresentationKey::Suffix(String::new()), format)],
+            utc: false,
+        }
+    }
+    /// Writes `_TIMESTAMP` as RFC 3339, `_TIMESTAMP_UNIX` and `_DATE`
+    pub fn all() -> Self {
+        Self::default()
+            .with_suffix("_UNIX", TimestampFormat::Unix)
+            .with_renamed("DATE", TimestampFormat::Date)
+    }
+    /// Also writes timestamps under the key of the item followed by `suffix`
+    pub fn with_suffix(mut self, suffix: impl Into<String>, format: TimestampFormat) -> Self {
+        self.representations
+            .push((RepresentationKey::Suffix(suffix.into()), format));
+        self
+    }
+    /// Also writes timestamps under the key of the item with a trailing `TIMESTAMP` replaced by `name`
+    ///
+    /// Keys not ending in `TIMESTAMP` get `_{name}` appended.
+    pub fn with_renamed(mut self, name: impl Into<String>, format: TimestampFormat) -> Self {
+        self.representations
+            .push((RepresentationKey::Rename(name.into()), format));
+        self
+    }
+    /// Rather or not to convert timestamps to UTC before formatting them
+    pub fn utc(mut self, utc: bool) -> Self {
+        self.utc = utc;
+        self
+    }
+    /// The environment variables for the timestamp stored under `key`
+    pub fn format(&self, key: &str, timestamp: &DateTime<FixedOffset>) -> Vec<(String, String)> {
+        let timestamp = if self.utc {
+            timestamp.with_timezone(&FixedOffset::east_opt(0).expect("UTC is a valid offset"))
+        } else {
+            *timestamp
+        };
+        self.representations
+            .iter()
+            .map(|(name, format)| (name.key(key), format.format(&timestamp)))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::DateTime;
+
+    use super::{TimestampFormat, TimestampPolicy};
+
+    #[test]
+    pub fn formats() {
+        let timestamp = DateTime::parse_from_rfc3339("2024-05-01T23:30:00-02:00").unwrap();
+        assert_eq!(
+            TimestampFormat::Rfc3339.format(&timestamp),
+            "2024-05-01T23:30:00-02:00"
+        );
+        assert_eq!(TimestampFormat::Unix.format(&timestamp), "1714613400");
+        assert_eq!(TimestampFormat::Date.format(&timestamp), "2024-05-01");
+        let strftime = TimestampFormat::strftime("%Y%m%d%H%M").unwrap();
+        assert_eq!(strftime.format(&timestamp), "202405012330");
+        assert!(TimestampFormat::strftime("%Y %Q").is_err());
+    }
+    #[test]
+    pub fn utc_policy() {
+        let timestamp = DateTime::parse_from_rfc3339("2024-05-01T23:30:00-02:00").unwrap();
+        let values = TimestampPolicy::all()
+            .utc(true)
+            .format("COMMIT_TIMESTAMP", &timestamp);
+        assert_eq!(
+            values,
+            vec![
+                (
+                    "COMMIT_TIMESTAMP".to_owned(),
+                    "2024-05-02T01:30:00+00:00".to_owned()
+                ),
+                ("COMMIT_TIMESTAMP_UNIX".to_owned(), "1714613400".to_owned()),
+                ("COMMIT_DATE".to_owned(), "2024-05-02".to_owned()),
+            ]
+        );
+        let values = TimestampPolicy::all().format("BUILT_AT", &timestamp);
+        assert_eq!(values[2].0, "BUILT_AT_DATE");
+    }
+}
@@ -2,6 +2,8 @@ use ahash::HashMap;
 use chrono::FixedOffset;
 use derive_more::From;
 use serde::{de::Visitor, Deserialize, Serialize};
+
+use crate::TimestampPolicy;
 /// A value used for the environment variables
 #[derive(From, Clone, Debug, PartialEq)]
 pub enum Value {
@@ -34,41 +36,53 @@ macro_rules! from_num {
         $(from_num!(Float => $num);)*
     };
 }
+impl From<&str> for Value {
+    fn from(value: &str) -> Self {
+        Value::String(value.to_owned())
+    }
+}
 from_num!(Numbers => i8, i16, i32, u8, u16, u32, u64);
 from_num!(Floats => f32);
 
 impl Value {
-    pub fn add_to_env(&self, key: &str) {
+    /// The value as it is written to the environment with the default [TimestampPolicy]
+    pub fn to_env_value(&self) -> String {
         match self {
-            Value::String(value) => crate::add_to_env(key, value),
-            Value::Bool(value) => crate::add_to_env(key, &value.to_string()),
+            Value::String(value) => value.to_owned(),
+            Value::Bool(value) => value.to_string(),
             Value::Float(float) => {
                 let mut buffer = dtoa::Buffer::new();
-                crate::add_to_env(key, buffer.format(*float))
+                buffer.format(*float).to_owned()
             }
             Value::Number(value) => {
                 let mut buffer = itoa::Buffer::new();
-                crate::add_to_env(key, buffer.format(*value))
+                buffer.format(*value).to_owned()
             }
-            Value::Char(c) => crate::add_to_env(key, &c.to_string()),
-            Value::DateTime(date_time) => crate::add_to_env(key, &date_time.to_rfc3339()),
+            Value::Char(c) => c.to_string(),
+            Value::DateTime(date_time) => date_time.to_rfc3339(),
         }
     }
-    pub fn add_to_map(&self, key: &str, map: &mut HashMap<String, String>) {
+    /// The environment variables of the value stored under `key`
+    ///
+    /// Timestamps are written once per representation of the policy.
+    pub fn to_env_values(&self, key: &str, policy: &TimestampPolicy) -> Vec<(String, String)> {
         match self {
-            Value::String(value) => map.insert(key.to_owned(), value.to_owned()),
-            Value::Bool(value) => map.insert(key.to_owned(), value.to_string()),
-            Value::Float(float) => {
-                let mut buffer = dtoa::Buffer::new();
-                map.insert(key.to_owned(), buffer.format(*float).to_owned())
-            }
-            Value::Number(value) => {
-                let mut buffer = itoa::Buffer::new();
-                map.insert(key.to_owned(), buffer.format(*value).to_owned())
-            }
-            Value::Char(c) => map.insert(key.to_owned(), c.to_string()),
-            Value::DateTime(date_time) => map.insert(key.to_owned(), date_time.to_rfc3339()),
-        };
+            Value::DateTime(date_time) => policy.format(key, date_time),
+            other => vec![(key.to_owned(), other.to_env_value())],
+        }
+    }
+    pub fn add_to_env(&self, key: &str, policy: &TimestampPolicy) {
+        for (key, value) in self.to_env_values(key, policy) {
+            crate::add_to_env(&key, &value);
+        }
+    }
+    pub fn add_to_map(
+        &self,
+        key: &str,
+        map: &mut HashMap<String, String>,
+        policy: &TimestampPolicy,
+    ) {
+        map.extend(self.to_env_values(key, policy));
     }
 }
 impl Serialize for Value {
@@ -132,7 +146,7 @@ impl<'de> Visitor<'de> for ValueVisitor {
         E: serde::de::Error,
     {
         if let Ok(date_time) = chrono::DateTime::parse_from_rfc3339(&v) {
-            return Ok(Value::DateTime(date_time));
+            Ok(Value::DateTime(date_time))
         } else {
             Ok(Value::String(v))
         }
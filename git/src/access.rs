@@ -0,0 +1,156 @@
+use std::{
+    fmt::Display,
+    str::FromStr,
+    sync::{Arc, Mutex},
+};
+
+use crate::{
+    CommitCount, DiscoveredRepository, GitHistory, GitSnapshot, GitSubmodule, GitUpstream,
+};
+
+/// A backend the git plugin reads the repository with
+///
+/// The built-in gix, git2 and git command line backends implement this trait.
+/// Implement it to read from another source and pass it to [GitPluginBuilder::backend](crate::GitPluginBuilder::backend).
+/// Fields a backend can not provide are returned as `None` and replaced by the fallbacks of the plugin.
+pub trait GitAccess: Send {
+    /// Loads every field at once. `HEAD` is only resolved a single time
+    fn snapshot(&self) -> anyhow::Result<GitSnapshot>;
+    /// Lists the submodules registered in `.gitmodules`
+    fn submodules(&self) -> anyhow::Result<Vec<GitSubmodule>>;
+    /// Checks if the repository is a shallow or partial clone. `None` if there is no repository
+    fn history(&self) -> anyhow::Result<Option<GitHistory>>;
+    /// The local branches followed by the remote tracking branches that point at `HEAD`.
+    ///
+    /// Remote branches are returned without the remote name. `origin/main` becomes `main`.
+    /// Every name is returned once, at its first position
+    fn branches_at_head(&self) -> anyhow::Result<Vec<String>>;
+    /// Counts the commits reachable from `HEAD` as configured by `options`.
+    ///
+    /// Fails with [IncompleteHistoryError](crate::IncompleteHistoryError) on shallow clones
+    fn get_commit_count(&self, options: &CommitCount) -> anyhow::Result<u64>;
+    /// The configured URL of the remote. `None` if the remote does not exist
+    fn remote_url(&self, remote: &str) -> anyhow::Result<Option<String>>;
+    /// The upstream of the current branch from the local refs. Nothing is fetched.
+    ///
+    /// `None` if `HEAD` is detached, no upstream is configured or the upstream branch does not exist locally
+    fn upstream(&self) -> anyhow::Result<Option<GitUpstream>>;
+}
+/// A built-in backend that opens the repository found by [discover](crate::discover)
+pub(crate) trait LoadGitAccess: GitAccess + Sized {
+    fn load(repository: &DiscoveredRepository) -> anyhow::Result<Self>;
+}
+/// A built-in backend. Each is only available if its feature of the same name is enabled
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GitBackend {
+    /// gitoxide, enabled by default
+    Gix,
+    /// libgit2
+    Git2,
+    /// The `git` command found in `PATH`
+    Cli,
+}
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+pub enum GitBackendError {
+    #[error("Unknown git backend {0:?}. Expected gix, git2 or cli")]
+    Unknown(String),
+    #[error("The {0} git backend is not available. Enable the `{0}` feature of smol-vergen-git")]
+    Unavailable(GitBackend),
+}
+impl GitBackend {
+    pub const ALL: [GitBackend; 3] = [GitBackend::Gix, GitBackend::Git2, GitBackend::Cli];
+    /// The name of the backend and its feature
+    pub fn name(self) -> &'static str {
+        match self {
+            GitBackend::Gix => "gix",
+            GitBackend::Git2 => "git2",
+            GitBackend::Cli => "cli",
+        }
+    }
+    /// Whether the feature of the backend is enabled
+    pub fn is_available(self) -> bool {
+        match self {
+            GitBackend::Gix => cfg!(feature = "gix"),
+            GitBackend::Git2 => cfg!(feature = "git2"),
+            GitBackend::Cli => cfg!(feature = "cli"),
+        }
+    }
+    pub(crate) fn load(
+        self,
+        repository: &DiscoveredRepository,
+    ) -> anyhow::Result<Box<dyn GitAccess>> {
+        match self {
+            #[cfg(feature = "gix")]
+            GitBackend::Gix => Ok(Box::new(crate::gitoxide_access::GitoxideAccess::load(
+                repository,
+            )?)),
+            #[cfg(feature = "git2")]
+            GitBackend::Git2 => Ok(Box::new(crate::native_git_access::NativeGitAccess::load(
+                repository,
+            )?)),
+            #[cfg(feature = "cli")]
+            GitBackend::Cli => Ok(Box::new(crate::cli_access::CLIGitAccess::load(repository)?)),
+            #[allow(unreachable_patterns)]
+            backend => Err(GitBackendError::Unavailable(backend).into()),
+        }
+    }
+}
+impl Display for GitBackend {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+impl FromStr for GitBackend {
+    type Err = GitBackendError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        GitBackend::ALL
+            .into_iter()
+            .find(|backend| backend.name() == s)
+            .ok_or_else(|| GitBackendError::Unknown(s.to_owned()))
+    }
+}
+/// A custom backend shared by every plugin loaded from the same [GitPlugin](crate::GitPlugin)
+#[derive(Clone)]
+pub(crate) struct SharedGitAccess(Arc<Mutex<Box<dyn GitAccess>>>);
+impl SharedGitAccess {
+    pub fn new(access: Box<dyn GitAccess>) -> Self {
+        Self(Arc::new(Mutex::new(access)))
+    }
+    fn with<T>(&self, f: impl FnOnce(&dyn GitAccess) -> anyhow::Result<T>) -> anyhow::Result<T> {
+        let access = self
+            .0
+            .lock()
+            .map_err(|_| anyhow::anyhow!("A git backend panicked"))?;
+        f(access.as_ref())
+    }
+}
+impl GitAccess for SharedGitAccess {
+    fn snapshot(&self) -> anyhow::Result<GitSnapshot> {
+        self.with(|access| access.snapshot())
+    }
+
+    fn submodules(&self) -> anyhow::Result<Vec<GitSubmodule>> {
+        self.with(|access| access.submodules())
+    }
+
+    fn history(&self) -> anyhow::Result<Option<GitHistory>> {
+        self.with(|access| access.history())
+    }
+
+    fn branches_at_head(&self) -> anyhow::Result<Vec<String>> {
+        self.with(|access| access.branches_at_head())
+    }
+
+    fn get_commit_count(&self, options: &CommitCount) -> anyhow::Result<u64> {
+        self.with(|access| access.get_commit_count(options))
+    }
+
+    fn remote_url(&self, remote: &str) -> anyhow::Result<Option<String>> {
+        self.with(|access| access.remote_url(remote))
+    }
+
+    fn upstream(&self) -> anyhow::Result<Option<GitUpstream>> {
+        self.with(|access| access.upstream())
+    }
+}
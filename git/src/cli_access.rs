@@ -1,133 +1,334 @@
-use chrono::{DateTime, FixedOffset};
+use chrono::DateTime;
 
-use crate::GitAcesss;
-use std::path::PathBuf;
+use crate::{
+    access::{GitAccess, LoadGitAccess},
+    CommitCount, DiscoveredRepository, GitHistory, GitSnapshot, GitSubmodule, GitUpstream,
+    IncompleteHistoryError,
+};
+use std::{path::PathBuf, process::Command};
 #[derive(thiserror::Error, Debug)]
 pub enum CLIError {
     #[error("Git command not found")]
     GitCommandNotFound,
     #[error("UTF8 error")]
     UTF8Error(#[from] std::string::FromUtf8Error),
+    #[error("Unexpected output from git: {0}")]
+    UnexpectedOutput(String),
+    #[error("Revision {0} not found")]
+    RevisionNotFound(String),
+    #[error("git {0} failed: {1}")]
+    CommandFailed(&'static str, String),
 }
-#[derive(Default, Clone)]
+/// The format passed to `git show`. Fields are separated by NUL and the raw message is last
+static SNAPSHOT_FORMAT: &str =
+    "--format=%H%x00%h%x00%an%x00%ae%x00%aI%x00%cn%x00%ce%x00%cI%x00%D%x00%s%x00%B";
+#[derive(Clone)]
 pub struct CLIGitAccess {
     git_command: PathBuf,
-    directory: PathBuf,
+    repository: DiscoveredRepository,
 }
-impl GitAcesss for CLIGitAccess {
-    type Error = CLIError;
-    fn load(directory: PathBuf) -> Result<Self, Self::Error>
-    where
-        Self: Sized,
-    {
+impl LoadGitAccess for CLIGitAccess {
+    fn load(repository: &DiscoveredRepository) -> anyhow::Result<Self> {
         let which = which::which("git").map_err(|_| CLIError::GitCommandNotFound)?;
         Ok(Self {
             git_command: which,
-            directory,
+            repository: repository.clone(),
         })
     }
-    fn get_branch(&self) -> Result<Option<String>, Self::Error> {
-        let output = std::process::Command::new(&self.git_command)
-            .arg("rev-parse")
-            .arg("--abbrev-ref")
+}
+impl GitAccess for CLIGitAccess {
+    fn snapshot(&self) -> anyhow::Result<GitSnapshot> {
+        let output = self
+            .git()
+            .arg("show")
+            .arg("-s")
+            .arg("--no-show-signature")
+            .arg(SNAPSHOT_FORMAT)
             .arg("HEAD")
-            .current_dir(&self.directory)
             .output()
             .map_err(|_| CLIError::GitCommandNotFound)?;
-        if output.status.success() {
-            let branch = String::from_utf8(output.stdout)?
-                .trim_end_matches('\n')
-                .to_owned();
-            Ok(Some(branch))
-        } else {
-            Ok(None)
+        if !output.status.success() {
+            if self.rev_parse(self.git(), "HEAD")?.is_none() {
+                return self.unborn_snapshot();
+            }
+            let stderr = String::from_utf8_lossy(&output.stderr).trim().to_owned();
+            return Err(CLIError::CommandFailed("show", stderr).into());
         }
+        let output = String::from_utf8(output.stdout)?;
+        let output = output.trim_end_matches('\n');
+        let fields: Vec<&str> = output.splitn(11, '\0').collect();
+        let [commit, commit_short, name, email, author_timestamp, committer_name, committer_email, timestamp, refs, message, full_message] =
+            fields[..]
+        else {
+            return Err(CLIError::UnexpectedOutput(output.to_owned()).into());
+        };
+        let detached = refs.split(", ").any(|name| name == "HEAD");
+        Ok(GitSnapshot {
+            branch: branch_from_refs(refs),
+            detached: Some(detached),
+            commit: Some(commit.to_owned()),
+            commit_short: Some(commit_short.to_owned()),
+            author: Some(crate::GitAuthor {
+                name: name.to_owned(),
+                email: email.to_owned(),
+            }),
+            message: Some(message.to_owned()),
+            full_message: Some(full_message.trim_end().to_owned()),
+            timestamp: DateTime::parse_from_rfc3339(timestamp).ok(),
+            committer: Some(crate::GitAuthor {
+                name: committer_name.to_owned(),
+                email: committer_email.to_owned(),
+            }),
+            author_timestamp: DateTime::parse_from_rfc3339(author_timestamp).ok(),
+            signed: Some(self.is_signed(commit)?),
+            dirty: None,
+        })
     }
 
-    fn get_commit(&self) -> Result<Option<String>, Self::Error> {
-        let output = std::process::Command::new(&self.git_command)
-            .arg("rev-parse")
-            .arg("HEAD")
-            .current_dir(&self.directory)
+    fn submodules(&self) -> anyhow::Result<Vec<GitSubmodule>> {
+        let Some(work_tree) = &self.repository.work_tree else {
+            return Ok(Vec::new());
+        };
+        if !work_tree.join(".gitmodules").is_file() {
+            return Ok(Vec::new());
+        }
+        let paths = self
+            .git()
+            .args(["config", "--file", ".gitmodules", "--get-regexp"])
+            .arg(r"^submodule\..*\.path$")
             .output()
             .map_err(|_| CLIError::GitCommandNotFound)?;
-        if output.status.success() {
-            let commit = String::from_utf8(output.stdout)?
-                .trim_end_matches('\n')
-                .to_owned();
-            Ok(Some(commit))
-        } else {
-            Ok(None)
+        let paths = String::from_utf8(paths.stdout)?;
+        let mut submodules = Vec::new();
+        for line in paths.lines() {
+            let Some((_, path)) = line.split_once(' ') else {
+                return Err(CLIError::UnexpectedOutput(line.to_owned()).into());
+            };
+            let recorded_commit = self.rev_parse(self.git(), &format!("HEAD:{path}"))?;
+            let submodule_dir = work_tree.join(path);
+            let commit = if submodule_dir.join(".git").exists() {
+                let mut command = Command::new(&self.git_command);
+                command.arg("-C").arg(&submodule_dir);
+                self.rev_parse(command, "HEAD")?
+            } else {
+                None
+            };
+            submodules.push(GitSubmodule::new(path.to_owned(), recorded_commit, commit));
         }
+        Ok(submodules)
     }
 
-    fn get_commit_short(&self) -> Result<Option<String>, Self::Error> {
-        let output = std::process::Command::new(&self.git_command)
-            .arg("rev-parse")
-            .arg("--short")
-            .arg("HEAD")
-            .current_dir(&self.directory)
+    fn history(&self) -> anyhow::Result<Option<GitHistory>> {
+        let shallow = self
+            .git()
+            .args(["rev-parse", "--is-shallow-repository"])
             .output()
             .map_err(|_| CLIError::GitCommandNotFound)?;
-        if output.status.success() {
-            let commit = String::from_utf8(output.stdout)?
-                .trim_end_matches('\n')
-                .to_owned();
-            Ok(Some(commit))
-        } else {
-            Ok(None)
-        }
+        let shallow = match String::from_utf8(shallow.stdout)?.trim() {
+            "true" => true,
+            "false" => false,
+            other => return Err(CLIError::UnexpectedOutput(other.to_owned()).into()),
+        };
+        let config = self
+            .git()
+            .args(["config", "--bool", "--get-regexp"])
+            .arg(r"^remote\..*\.promisor$")
+            .output()
+            .map_err(|_| CLIError::GitCommandNotFound)?;
+        let partial_clone = self
+            .git()
+            .args(["config", "--get", "extensions.partialclone"])
+            .output()
+            .map_err(|_| CLIError::GitCommandNotFound)?;
+        let partial = partial_clone.status.success()
+            || String::from_utf8(config.stdout)?
+                .lines()
+                .any(|line| line.ends_with(" true"));
+        Ok(Some(GitHistory { shallow, partial }))
     }
 
-    fn get_commit_author(&self) -> Result<Option<crate::GitAuthor>, Self::Error> {
-        let output = std::process::Command::new(&self.git_command)
-            .arg("show")
-            .arg("-s")
-            .arg("--format=%an <%ae>")
-            .arg("HEAD")
-            .current_dir(&self.directory)
+    fn branches_at_head(&self) -> anyhow::Result<Vec<String>> {
+        let output = self
+            .git()
+            .args(["for-each-ref", "--points-at", "HEAD", "--format=%(refname)"])
+            .args(["refs/heads", "refs/remotes"])
             .output()
             .map_err(|_| CLIError::GitCommandNotFound)?;
-        if output.status.success() {
-            let author = String::from_utf8(output.stdout).unwrap();
-            let mut split = author.splitn(2, " <");
-            if split.clone().count() != 2 {
-                return Ok(None);
+        if !output.status.success() {
+            return Ok(Vec::new());
+        }
+        let output = String::from_utf8(output.stdout)?;
+        // refs/heads sorts before refs/remotes so local branches come first
+        let mut branches: Vec<String> = Vec::new();
+        let names = output
+            .lines()
+            .filter_map(|reference| match reference.strip_prefix("refs/heads/") {
+                Some(branch) => Some(branch),
+                None => reference
+                    .strip_prefix("refs/remotes/")?
+                    .split_once('/')
+                    .map(|(_, branch)| branch),
+            })
+            .filter(|branch| *branch != "HEAD");
+        for name in names {
+            if !branches.iter().any(|known| known == name) {
+                branches.push(name.to_owned());
             }
-            let name = split.next().unwrap().to_string();
-            let email = split
-                .next()
-                .unwrap()
-                .to_string()
-                .trim_end_matches(">\n")
-                .to_string();
-            Ok(Some(crate::GitAuthor { name, email }))
-        } else {
-            Ok(None)
         }
+        Ok(branches)
     }
 
-    fn get_commit_message(&self) -> Result<Option<String>, Self::Error> {
-        let output = std::process::Command::new(&self.git_command)
-            .arg("show")
-            .arg("-s")
-            .arg("--format=%s")
-            .arg("HEAD")
-            .current_dir(&self.directory)
+    fn get_commit_count(&self, options: &CommitCount) -> anyhow::Result<u64> {
+        if self.history()?.is_some_and(|history| history.shallow) {
+            return Err(IncompleteHistoryError {
+                feature: "commit count",
+            }
+            .into());
+        }
+        if self.rev_parse(self.git(), "HEAD")?.is_none() {
+            return Ok(0);
+        }
+        let mut command = self.git();
+        command.args(["rev-list", "--count"]);
+        if options.first_parent {
+            command.arg("--first-parent");
+        }
+        command.arg("HEAD");
+        if let Some(revision) = options.hidden_revision() {
+            let hidden = self
+                .rev_parse(self.git(), &revision)?
+                .ok_or(CLIError::RevisionNotFound(revision))?;
+            command.arg(format!("^{}", hidden));
+        }
+        let output = command.output().map_err(|_| CLIError::GitCommandNotFound)?;
+        let output = String::from_utf8(output.stdout)?;
+        output
+            .trim()
+            .parse()
+            .map_err(|_| CLIError::UnexpectedOutput(output).into())
+    }
+
+    fn remote_url(&self, remote: &str) -> anyhow::Result<Option<String>> {
+        let output = self
+            .git()
+            .args(["config", "--get"])
+            .arg(format!("remote.{}.url", remote))
             .output()
             .map_err(|_| CLIError::GitCommandNotFound)?;
-        if output.status.success() {
-            let message = String::from_utf8(output.stdout)?
-                .trim_end_matches('\n')
-                .to_owned();
-            Ok(Some(message))
-        } else {
-            Ok(None)
+        if !output.status.success() {
+            return Ok(None);
         }
+        Ok(Some(String::from_utf8(output.stdout)?.trim().to_owned()))
     }
 
-    fn get_commit_timestamp(&self) -> Result<Option<DateTime<FixedOffset>>, Self::Error> {
-        // TODO implement
-        Ok(None)
+    fn upstream(&self) -> anyhow::Result<Option<GitUpstream>> {
+        let name = self
+            .git()
+            .args([
+                "rev-parse",
+                "--abbrev-ref",
+                "--symbolic-full-name",
+                "@{upstream}",
+            ])
+            .output()
+            .map_err(|_| CLIError::GitCommandNotFound)?;
+        // Fails if `HEAD` is detached, there is no upstream or it does not exist locally
+        if !name.status.success() {
+            return Ok(None);
+        }
+        if self.history()?.is_some_and(|history| history.shallow) {
+            return Err(IncompleteHistoryError {
+                feature: "ahead and behind counts",
+            }
+            .into());
+        }
+        let counts = self
+            .git()
+            .args(["rev-list", "--left-right", "--count", "HEAD...@{upstream}"])
+            .output()
+            .map_err(|_| CLIError::GitCommandNotFound)?;
+        let counts = String::from_utf8(counts.stdout)?;
+        let Some((ahead, behind)) = counts
+            .split_once('\t')
+            .and_then(|(ahead, behind)| Some((ahead.parse().ok()?, behind.trim().parse().ok()?)))
+        else {
+            return Err(CLIError::UnexpectedOutput(counts).into());
+        };
+        Ok(Some(GitUpstream {
+            name: String::from_utf8(name.stdout)?.trim().to_owned(),
+            ahead,
+            behind,
+        }))
     }
 }
+impl CLIGitAccess {
+    /// A git command pointed at the discovered repository
+    fn git(&self) -> Command {
+        let mut command = Command::new(&self.git_command);
+        command
+            .arg("--git-dir")
+            .arg(&self.repository.git_dir)
+            .current_dir(self.repository.root());
+        if let Some(work_tree) = &self.repository.work_tree {
+            command.arg("--work-tree").arg(work_tree);
+        }
+        command
+    }
+    /// The snapshot of an unborn branch. Empty if `HEAD` can not be read at all
+    fn unborn_snapshot(&self) -> anyhow::Result<GitSnapshot> {
+        let output = self
+            .git()
+            .args(["symbolic-ref", "--quiet", "--short", "HEAD"])
+            .output()
+            .map_err(|_| CLIError::GitCommandNotFound)?;
+        if !output.status.success() {
+            return Ok(GitSnapshot::default());
+        }
+        Ok(GitSnapshot {
+            branch: Some(String::from_utf8(output.stdout)?.trim().to_owned()),
+            detached: Some(false),
+            ..Default::default()
+        })
+    }
+    /// Checks the raw commit headers for a signature without verifying it
+    fn is_signed(&self, commit: &str) -> Result<bool, CLIError> {
+        let output = self
+            .git()
+            .args(["cat-file", "commit", commit])
+            .output()
+            .map_err(|_| CLIError::GitCommandNotFound)?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr).trim().to_owned();
+            return Err(CLIError::CommandFailed("cat-file", stderr));
+        }
+        let output = String::from_utf8(output.stdout)?;
+        Ok(output
+            .lines()
+            .take_while(|line| !line.is_empty())
+            .filter_map(|line| line.split_once(' '))
+            .any(|(header, _)| crate::SIGNATURE_HEADERS.contains(&header)))
+    }
+    /// Runs `git rev-parse` returning `None` if the revision does not exist
+    fn rev_parse(&self, mut command: Command, revision: &str) -> Result<Option<String>, CLIError> {
+        let output = command
+            .arg("rev-parse")
+            .arg("--verify")
+            .arg("--quiet")
+            .arg(revision)
+            .output()
+            .map_err(|_| CLIError::GitCommandNotFound)?;
+        if !output.status.success() {
+            return Ok(None);
+        }
+        Ok(Some(String::from_utf8(output.stdout)?.trim().to_owned()))
+    }
+}
+/// Finds the branch in the `%D` ref names. `HEAD -> main, origin/main` becomes `main`
+///
+/// A detached `HEAD` is listed as `HEAD` and has no branch.
+fn branch_from_refs(refs: &str) -> Option<String> {
+    refs.split(", ").find_map(|name| {
+        name.strip_prefix("HEAD -> ")
+            .map(|branch| branch.to_owned())
+    })
+}
@@ -0,0 +1,31 @@
+/// Where [CommitCount] starts counting
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum CommitCountBase {
+    /// Every commit reachable from `HEAD`
+    #[default]
+    All,
+    /// Commits since the tag. `v1.0.0` counts `v1.0.0..HEAD`
+    Tag(String),
+    /// Commits on `HEAD` that are not on the mainline branch.
+    ///
+    /// For a branch created from the mainline this counts the commits since the merge base.
+    /// Remote branches such as `origin/main` are allowed
+    Mainline(String),
+}
+/// Counts commits to produce a monotonically increasing build number
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CommitCount {
+    /// Only follow the first parent of merge commits, like `git rev-list --first-parent`
+    pub first_parent: bool,
+    pub base: CommitCountBase,
+}
+impl CommitCount {
+    /// The revision whose history is excluded from the count
+    pub(crate) fn hidden_revision(&self) -> Option<String> {
+        match &self.base {
+            CommitCountBase::All => None,
+            CommitCountBase::Tag(tag) => Some(format!("refs/tags/{}^{{commit}}", tag)),
+            CommitCountBase::Mainline(branch) => Some(format!("{}^{{commit}}", branch)),
+        }
+    }
+}
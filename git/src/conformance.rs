@@ -0,0 +1,190 @@
+//! Runs the same fixtures through every compiled backend and requires identical results
+use chrono::{DateTime, FixedOffset, TimeZone};
+
+use crate::{
+    access::{GitAccess, LoadGitAccess},
+    fixture::GitFixture,
+    CommitCount, GitHistory, GitSnapshot, GitUpstream,
+};
+
+type LoadBackend = fn(&GitFixture) -> anyhow::Result<Box<dyn GitAccess>>;
+
+fn load<G: LoadGitAccess + 'static>(fixture: &GitFixture) -> anyhow::Result<Box<dyn GitAccess>> {
+    Ok(Box::new(fixture.load::<G>()?))
+}
+/// Every backend compiled into the tests
+fn backends() -> Vec<(&'static str, LoadBackend)> {
+    vec![
+        #[cfg(feature = "gix")]
+        ("gix", load::<crate::gitoxide_access::GitoxideAccess>),
+        #[cfg(feature = "git2")]
+        ("git2", load::<crate::native_git_access::NativeGitAccess>),
+        #[cfg(feature = "cli")]
+        ("cli", load::<crate::cli_access::CLIGitAccess>),
+    ]
+}
+/// What a backend reports about a fixture. Errors are only compared by whether they occurred
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Observation {
+    snapshot: Option<GitSnapshot>,
+    /// `DateTime` ignores the offset when compared, so the timestamps are compared as text as well
+    timestamps: (Option<String>, Option<String>),
+    branches_at_head: Option<Vec<String>>,
+    commit_count: Option<u64>,
+    upstream: Option<Option<GitUpstream>>,
+    history: Option<Option<GitHistory>>,
+}
+impl Observation {
+    fn new(access: &dyn GitAccess) -> Self {
+        let snapshot = access.snapshot().ok();
+        let rfc3339 = |timestamp: Option<DateTime<FixedOffset>>| {
+            timestamp.map(|timestamp| timestamp.to_rfc3339())
+        };
+        let timestamps = snapshot.as_ref().map_or((None, None), |snapshot| {
+            (
+                rfc3339(snapshot.timestamp),
+                rfc3339(snapshot.author_timestamp),
+            )
+        });
+        Self {
+            snapshot,
+            timestamps,
+            branches_at_head: access.branches_at_head().ok(),
+            commit_count: access.get_commit_count(&CommitCount::default()).ok(),
+            upstream: access.upstream().ok(),
+            history: access.history().ok(),
+        }
+    }
+}
+/// Observes `fixture` with every backend. Panics naming the backends that disagree
+fn observe(fixture: &GitFixture) -> anyhow::Result<Observation> {
+    let mut observations = Vec::new();
+    for (name, load) in backends() {
+        observations.push((name, Observation::new(load(fixture)?.as_ref())));
+    }
+    let (first_name, first) = observations.remove(0);
+    for (name, observation) in observations {
+        assert_eq!(first, observation, "{} and {} disagree", first_name, name);
+    }
+    Ok(first)
+}
+fn time(offset_seconds: i32, seconds: i64) -> DateTime<FixedOffset> {
+    FixedOffset::east_opt(offset_seconds)
+        .and_then(|offset| offset.timestamp_opt(seconds, 0).single())
+        .expect("valid timestamp")
+}
+
+#[test]
+pub fn conformance_timezones() -> anyhow::Result<()> {
+    let fixture = GitFixture::empty()?;
+    for (author, committer) in [
+        (2 * 3600, -5 * 3600),
+        (-(3 * 3600 + 1800), 5 * 3600 + 1800),
+        (0, 14 * 3600),
+    ] {
+        let mut commit = fixture.next_commit("zone");
+        commit.author_time = time(author, 1_700_000_000);
+        commit.commit_time = time(committer, 1_700_003_600);
+        fixture.commit_with(commit.clone())?;
+        let observation = observe(&fixture)?;
+        assert_eq!(
+            observation.timestamps,
+            (
+                Some(commit.commit_time.to_rfc3339()),
+                Some(commit.author_time.to_rfc3339())
+            )
+        );
+    }
+    Ok(())
+}
+#[test]
+pub fn conformance_titles() -> anyhow::Result<()> {
+    let fixture = GitFixture::empty()?;
+    for (message, title, full_message) in [
+        ("Single line", "Single line", "Single line"),
+        ("Single line\n", "Single line", "Single line"),
+        ("Title\n\nBody\n", "Title", "Title\n\nBody"),
+        (
+            "Wrapped\ntitle\n\nBody",
+            "Wrapped title",
+            "Wrapped\ntitle\n\nBody",
+        ),
+    ] {
+        fixture.commit(message)?;
+        let snapshot = observe(&fixture)?.snapshot.expect("snapshot");
+        assert_eq!(snapshot.message.as_deref(), Some(title), "{:?}", message);
+        assert_eq!(snapshot.full_message.as_deref(), Some(full_message));
+    }
+    Ok(())
+}
+#[test]
+pub fn conformance_branches() -> anyhow::Result<()> {
+    let fixture = GitFixture::new()?;
+    let commit = fixture.head()?;
+    for branch in ["zeta", "alpha", "feature/nested"] {
+        fixture.branch(branch)?;
+    }
+    fixture.annotated_tag("v1", "v1")?;
+    fixture.commit("ahead")?;
+    let observation = observe(&fixture)?;
+    assert_eq!(observation.branches_at_head, Some(vec!["main".to_owned()]));
+    assert_eq!(observation.commit_count, Some(2));
+    assert_eq!(observation.upstream, Some(None));
+    assert_eq!(observation.history, Some(Some(GitHistory::default())));
+
+    fixture.detach("v1")?;
+    let observation = observe(&fixture)?;
+    let snapshot = observation.snapshot.expect("snapshot");
+    assert_eq!(snapshot.branch, None);
+    assert_eq!(snapshot.detached, Some(true));
+    assert_eq!(snapshot.commit, Some(commit));
+    assert_eq!(
+        observation.branches_at_head,
+        Some(vec![
+            "alpha".to_owned(),
+            "feature/nested".to_owned(),
+            "zeta".to_owned()
+        ])
+    );
+    assert_eq!(observation.commit_count, Some(1));
+    assert_eq!(observation.upstream, Some(None));
+
+    fixture.checkout("feature/nested")?;
+    let snapshot = observe(&fixture)?.snapshot.expect("snapshot");
+    assert_eq!(snapshot.branch.as_deref(), Some("feature/nested"));
+    assert_eq!(snapshot.detached, Some(false));
+    Ok(())
+}
+/// An unborn branch has no commit but is still on a branch
+#[test]
+pub fn conformance_empty_repository() -> anyhow::Result<()> {
+    let fixture = GitFixture::empty()?;
+    let observation = observe(&fixture)?;
+    assert_eq!(
+        observation.snapshot,
+        Some(GitSnapshot {
+            branch: Some("main".to_owned()),
+            detached: Some(false),
+            ..Default::default()
+        })
+    );
+    assert_eq!(observation.branches_at_head, Some(Vec::new()));
+    assert_eq!(observation.commit_count, Some(0));
+    assert_eq!(observation.upstream, Some(None));
+    assert_eq!(observation.history, Some(Some(GitHistory::default())));
+    Ok(())
+}
+/// `HEAD` pointing at a missing commit is an error and not an unborn branch
+#[test]
+pub fn conformance_missing_head_commit() -> anyhow::Result<()> {
+    let fixture = GitFixture::new()?;
+    // git refuses to point a ref at a missing object
+    fixture.write(
+        ".git/refs/heads/main",
+        "1234567890123456789012345678901234567890\n",
+    )?;
+    for (name, load) in backends() {
+        assert!(load(&fixture)?.snapshot().is_err(), "{}", name);
+    }
+    Ok(())
+}
@@ -0,0 +1,271 @@
+use std::{
+    ffi::OsString,
+    path::{Path, PathBuf},
+};
+
+#[derive(thiserror::Error, Debug)]
+pub enum DiscoverError {
+    #[error("Failed to read {0}: {1}")]
+    Io(PathBuf, #[source] std::io::Error),
+    #[error("{0} is not a valid gitdir file")]
+    InvalidGitFile(PathBuf),
+}
+/// A repository found by [discover]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiscoveredRepository {
+    /// The root of the working tree. `None` for bare repositories
+    pub work_tree: Option<PathBuf>,
+    /// The git directory of the repository.
+    ///
+    /// For linked worktrees and submodules this is the directory the `.git` file points to.
+    pub git_dir: PathBuf,
+    /// The directory holding the objects and shared refs.
+    ///
+    /// Only differs from `git_dir` for linked worktrees.
+    pub common_dir: PathBuf,
+}
+impl DiscoveredRepository {
+    /// The directory that identifies the repository. The working tree or the git directory for bare repositories
+    pub fn root(&self) -> &Path {
+        self.work_tree.as_deref().unwrap_or(&self.git_dir)
+    }
+    fn from_git_dir(work_tree: Option<PathBuf>, git_dir: PathBuf) -> Result<Self, DiscoverError> {
+        let commondir = git_dir.join("commondir");
+        let common_dir = if commondir.is_file() {
+            let content = std::fs::read_to_string(&commondir)
+                .map_err(|err| DiscoverError::Io(commondir, err))?;
+            git_dir.join(content.trim())
+        } else {
+            git_dir.clone()
+        };
+        Ok(Self {
+            work_tree,
+            git_dir,
+            common_dir,
+        })
+    }
+}
+/// The environment variables git uses during discovery
+#[derive(Debug, Clone, Default)]
+pub(crate) struct DiscoverEnv {
+    pub git_dir: Option<PathBuf>,
+    pub work_tree: Option<PathBuf>,
+    pub ceiling_directories: Vec<PathBuf>,
+    pub across_filesystem: bool,
+}
+/// Environment variables that change which repository is discovered
+pub(crate) static DISCOVER_ENV_VARS: &[&str] = &[
+    "GIT_DIR",
+    "GIT_WORK_TREE",
+    "GIT_CEILING_DIRECTORIES",
+    "GIT_DISCOVERY_ACROSS_FILESYSTEM",
+];
+impl DiscoverEnv {
+    pub fn from_env() -> Self {
+        let var = |name: &str| std::env::var_os(name).filter(|value| !value.is_empty());
+        Self {
+            git_dir: var("GIT_DIR").map(PathBuf::from),
+            work_tree: var("GIT_WORK_TREE").map(PathBuf::from),
+            ceiling_directories: var("GIT_CEILING_DIRECTORIES")
+                .map(|value| std::env::split_paths(&value).collect())
+                .unwrap_or_default(),
+            across_filesystem: var("GIT_DISCOVERY_ACROSS_FILESYSTEM")
+                .map(is_true)
+                .unwrap_or_default(),
+        }
+    }
+}
+fn is_true(value: OsString) -> bool {
+    matches!(
+        value.to_string_lossy().to_lowercase().as_str(),
+        "1" | "true" | "yes" | "on"
+    )
+}
+/// Finds the repository `directory` belongs to, the same way git does
+///
+/// - `GIT_DIR` and `GIT_WORK_TREE` are used as is. Every backend opens the repository with this work tree
+/// - `.git` directories and `.git` files (linked worktrees and submodules) are followed
+/// - Bare repositories are detected
+/// - If `check_parents` is true parent directories are searched until a directory in
+///   `GIT_CEILING_DIRECTORIES` or a filesystem boundary is reached. Set
+///   `GIT_DISCOVERY_ACROSS_FILESYSTEM` to cross filesystems
+pub fn discover(
+    directory: &Path,
+    check_parents: bool,
+) -> Result<Option<DiscoveredRepository>, DiscoverError> {
+    discover_with_env(directory, check_parents, &DiscoverEnv::from_env())
+}
+pub(crate) fn discover_with_env(
+    directory: &Path,
+    check_parents: bool,
+    env: &DiscoverEnv,
+) -> Result<Option<DiscoveredRepository>, DiscoverError> {
+    if let Some(git_dir) = &env.git_dir {
+        let work_tree = match &env.work_tree {
+            Some(work_tree) => directory.join(work_tree),
+            None => directory.to_path_buf(),
+        };
+        return DiscoveredRepository::from_git_dir(Some(work_tree), directory.join(git_dir))
+            .map(Some);
+    }
+    let device = device(directory);
+    let ceiling_directories: Vec<PathBuf> = env
+        .ceiling_directories
+        .iter()
+        .map(|ceiling| canonicalize(ceiling))
+        .collect();
+    let mut current = directory.to_path_buf();
+    loop {
+        if let Some(repository) = repository_in(&current)? {
+            return Ok(Some(repository));
+        }
+        if !check_parents {
+            return Ok(None);
+        }
+        let Some(parent) = current.parent() else {
+            return Ok(None);
+        };
+        if ceiling_directories.contains(&canonicalize(parent)) {
+            return Ok(None);
+        }
+        if !env.across_filesystem && device.is_some() && device != self::device(parent) {
+            return Ok(None);
+        }
+        current = parent.to_path_buf();
+    }
+}
+/// Resolves `..` and symbolic links like git does for `GIT_CEILING_DIRECTORIES`. Paths that do not exist are kept as is
+fn canonicalize(path: &Path) -> PathBuf {
+    std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+/// Checks if `directory` is the root of a working tree or a bare repository
+fn repository_in(directory: &Path) -> Result<Option<DiscoveredRepository>, DiscoverError> {
+    let dot_git = directory.join(".git");
+    if dot_git.is_dir() {
+        return DiscoveredRepository::from_git_dir(Some(directory.to_path_buf()), dot_git)
+            .map(Some);
+    }
+    if dot_git.is_file() {
+        let content = std::fs::read_to_string(&dot_git)
+            .map_err(|err| DiscoverError::Io(dot_git.clone(), err))?;
+        let git_dir = content
+            .lines()
+            .find_map(|line| line.strip_prefix("gitdir:"))
+            .ok_or(DiscoverError::InvalidGitFile(dot_git))?;
+        let git_dir = directory.join(git_dir.trim());
+        return DiscoveredRepository::from_git_dir(Some(directory.to_path_buf()), git_dir)
+            .map(Some);
+    }
+    if is_bare_repository(directory) {
+        return DiscoveredRepository::from_git_dir(None, directory.to_path_buf()).map(Some);
+    }
+    Ok(None)
+}
+fn is_bare_repository(directory: &Path) -> bool {
+    directory.join("HEAD").is_file()
+        && directory.join("objects").is_dir()
+        && directory.join("refs").is_dir()
+}
+#[cfg(unix)]
+fn device(path: &Path) -> Option<u64> {
+    use std::os::unix::fs::MetadataExt;
+    std::fs::metadata(path).ok().map(|metadata| metadata.dev())
+}
+#[cfg(not(unix))]
+fn device(_: &Path) -> Option<u64> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::{discover_with_env, DiscoverEnv};
+
+    fn init_bare(path: &Path) {
+        std::fs::create_dir_all(path.join("objects")).unwrap();
+        std::fs::create_dir_all(path.join("refs")).unwrap();
+        std::fs::write(path.join("HEAD"), "ref: refs/heads/main\n").unwrap();
+    }
+    #[test]
+    pub fn test_parents() {
+        let dir = tempfile::tempdir().unwrap();
+        init_bare(&dir.path().join(".git"));
+        let nested = dir.path().join("a/b");
+        std::fs::create_dir_all(&nested).unwrap();
+        let env = DiscoverEnv::default();
+        assert_eq!(discover_with_env(&nested, false, &env).unwrap(), None);
+        let repository = discover_with_env(&nested, true, &env).unwrap().unwrap();
+        assert_eq!(repository.root(), dir.path());
+    }
+    #[test]
+    pub fn test_ceiling_directories() {
+        let dir = tempfile::tempdir().unwrap();
+        init_bare(&dir.path().join(".git"));
+        let nested = dir.path().join("a/b");
+        std::fs::create_dir_all(&nested).unwrap();
+        let ceilings = [
+            dir.path().join("a"),
+            dir.path().join("a/"),
+            dir.path().join("a/b/.."),
+        ];
+        for ceiling in ceilings {
+            let env = DiscoverEnv {
+                ceiling_directories: vec![ceiling.clone()],
+                ..Default::default()
+            };
+            assert_eq!(
+                discover_with_env(&nested, true, &env).unwrap(),
+                None,
+                "{}",
+                ceiling.display()
+            );
+        }
+    }
+    #[cfg(unix)]
+    #[test]
+    pub fn test_symlinked_ceiling_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        init_bare(&dir.path().join(".git"));
+        let nested = dir.path().join("a/b");
+        std::fs::create_dir_all(&nested).unwrap();
+        let link = dir.path().join("link");
+        std::os::unix::fs::symlink(dir.path().join("a"), &link).unwrap();
+        let env = DiscoverEnv {
+            ceiling_directories: vec![link],
+            ..Default::default()
+        };
+        assert_eq!(discover_with_env(&nested, true, &env).unwrap(), None);
+    }
+    #[test]
+    pub fn test_linked_worktree() {
+        let dir = tempfile::tempdir().unwrap();
+        let worktree_git_dir = dir.path().join("main/.git/worktrees/feature");
+        init_bare(&dir.path().join("main/.git"));
+        std::fs::create_dir_all(&worktree_git_dir).unwrap();
+        std::fs::write(worktree_git_dir.join("commondir"), "../..\n").unwrap();
+        let worktree = dir.path().join("feature");
+        std::fs::create_dir_all(&worktree).unwrap();
+        std::fs::write(
+            worktree.join(".git"),
+            format!("gitdir: {}\n", worktree_git_dir.display()),
+        )
+        .unwrap();
+        let repository = discover_with_env(&worktree, false, &DiscoverEnv::default())
+            .unwrap()
+            .unwrap();
+        assert_eq!(repository.root(), worktree);
+        assert_eq!(repository.git_dir, worktree_git_dir);
+        assert_eq!(repository.common_dir, worktree_git_dir.join("../.."));
+    }
+    #[test]
+    pub fn test_bare() {
+        let dir = tempfile::tempdir().unwrap();
+        init_bare(dir.path());
+        let repository = discover_with_env(dir.path(), false, &DiscoverEnv::default())
+            .unwrap()
+            .unwrap();
+        assert_eq!(repository.work_tree, None);
+        assert_eq!(repository.root(), dir.path());
+    }
+}
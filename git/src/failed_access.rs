@@ -1,64 +1,38 @@
-use std::{convert::Infallible, path::PathBuf};
-
-use chrono::{DateTime, FixedOffset};
-
-use crate::GitAcesss;
+use crate::{access::GitAccess, CommitCount, GitHistory, GitSnapshot, GitSubmodule, GitUpstream};
 
+#[derive(thiserror::Error, Debug)]
+pub enum FailedAccessError {
+    #[error("{0} is not available without a repository")]
+    Unsupported(&'static str),
+}
 #[derive(Debug, Clone, Default, Copy)]
 pub struct FailedAccess;
-impl GitAcesss for FailedAccess {
-    type Error = Infallible;
-
-    fn load(_: PathBuf) -> Result<Self, Self::Error>
-    where
-        Self: Sized,
-    {
-        Ok(Self)
+impl GitAccess for FailedAccess {
+    fn snapshot(&self) -> anyhow::Result<GitSnapshot> {
+        Ok(GitSnapshot::default())
     }
 
-    fn get_branch(&self) -> Result<Option<String>, Self::Error> {
-        Ok(branch())
+    fn submodules(&self) -> anyhow::Result<Vec<GitSubmodule>> {
+        Ok(Vec::new())
     }
 
-    fn get_commit(&self) -> Result<Option<String>, Self::Error> {
-        Ok(Some("Unknown".to_owned()))
+    fn history(&self) -> anyhow::Result<Option<GitHistory>> {
+        Ok(None)
     }
 
-    fn get_commit_short(&self) -> Result<Option<String>, Self::Error> {
-        Ok(commit_short())
+    fn branches_at_head(&self) -> anyhow::Result<Vec<String>> {
+        Ok(Vec::new())
     }
 
-    fn get_commit_author(&self) -> Result<Option<crate::GitAuthor>, Self::Error> {
-        Ok(commit_author())
+    fn get_commit_count(&self, _: &CommitCount) -> anyhow::Result<u64> {
+        Err(FailedAccessError::Unsupported("The commit count").into())
     }
 
-    fn get_commit_message(&self) -> Result<Option<String>, Self::Error> {
-        Ok(commit_message())
+    fn remote_url(&self, _: &str) -> anyhow::Result<Option<String>> {
+        Ok(None)
     }
 
-    fn get_commit_timestamp(&self) -> Result<Option<DateTime<FixedOffset>>, Self::Error> {
-        Ok(commit_timestamp())
+    fn upstream(&self) -> anyhow::Result<Option<GitUpstream>> {
+        Ok(None)
     }
 }
-pub(crate) fn branch() -> Option<String> {
-    Some("Unknown".to_owned())
-}
-
-pub(crate) fn commit() -> Option<String> {
-    Some("Unknown".to_owned())
-}
-pub(crate) fn commit_short() -> Option<String> {
-    Some("Unknown".to_owned())
-}
-pub(crate) fn commit_author() -> Option<crate::GitAuthor> {
-    Some(crate::GitAuthor {
-        name: "Unknown".to_owned(),
-        email: "Unknown".to_owned(),
-    })
-}
-pub(crate) fn commit_message() -> Option<String> {
-    Some("Unknown".to_owned())
-}
-pub(crate) fn commit_timestamp() -> Option<DateTime<FixedOffset>> {
-    Some(DateTime::default())
-}
@@ -0,0 +1,126 @@
+use chrono::{DateTime, FixedOffset};
+
+use crate::{GitAuthor, GitSnapshot};
+
+/// What to report for a field that could not be read from the repository
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Fallback<T> {
+    /// Leave the key out
+    Omit,
+    /// Report this value instead
+    Value(T),
+}
+impl<T> Fallback<T> {
+    fn or(self, value: Option<T>) -> Option<T> {
+        match self {
+            Fallback::Omit => value,
+            Fallback::Value(fallback) => value.or(Some(fallback)),
+        }
+    }
+}
+impl<T: Into<String>> From<T> for Fallback<String> {
+    fn from(value: T) -> Self {
+        Fallback::Value(value.into())
+    }
+}
+/// The values the git plugin reports if `provide_defaults_on_error` is set and a field could not be read
+///
+/// Every string defaults to `Unknown` and every timestamp to the Unix epoch.
+/// Use [GitFallbacks::omit] to leave missing keys out instead.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GitFallbacks {
+    /// Not used if `HEAD` is detached, a detached `HEAD` has no branch
+    pub branch: Fallback<String>,
+    pub commit: Fallback<String>,
+    pub commit_short: Fallback<String>,
+    pub author: Fallback<GitAuthor>,
+    pub committer: Fallback<GitAuthor>,
+    pub message: Fallback<String>,
+    pub timestamp: Fallback<DateTime<FixedOffset>>,
+    pub author_timestamp: Fallback<DateTime<FixedOffset>>,
+}
+impl Default for GitFallbacks {
+    fn default() -> Self {
+        let unknown = || Fallback::Value("Unknown".to_owned());
+        let unknown_author = || Fallback::Value(GitAuthor::new("Unknown", "Unknown"));
+        Self {
+            branch: unknown(),
+            commit: unknown(),
+            commit_short: unknown(),
+            author: unknown_author(),
+            committer: unknown_author(),
+            message: unknown(),
+            timestamp: Fallback::Value(DateTime::default()),
+            author_timestamp: Fallback::Value(DateTime::default()),
+        }
+    }
+}
+impl GitFallbacks {
+    /// Leaves every missing key out
+    pub fn omit() -> Self {
+        Self {
+            branch: Fallback::Omit,
+            commit: Fallback::Omit,
+            commit_short: Fallback::Omit,
+            author: Fallback::Omit,
+            committer: Fallback::Omit,
+            message: Fallback::Omit,
+            timestamp: Fallback::Omit,
+            author_timestamp: Fallback::Omit,
+        }
+    }
+    /// Replaces every missing field of `snapshot` with its fallback
+    pub(crate) fn fill(&self, snapshot: GitSnapshot) -> GitSnapshot {
+        let fallbacks = self.clone();
+        let branch = match snapshot.detached {
+            Some(true) => snapshot.branch,
+            _ => fallbacks.branch.or(snapshot.branch),
+        };
+        GitSnapshot {
+            branch,
+            detached: snapshot.detached,
+            commit: fallbacks.commit.or(snapshot.commit),
+            commit_short: fallbacks.commit_short.or(snapshot.commit_short),
+            author: fallbacks.author.or(snapshot.author),
+            message: fallbacks.message.or(snapshot.message),
+            full_message: snapshot.full_message,
+            timestamp: fallbacks.timestamp.or(snapshot.timestamp),
+            committer: fallbacks.committer.or(snapshot.committer),
+            author_timestamp: fallbacks.author_timestamp.or(snapshot.author_timestamp),
+            signed: snapshot.signed,
+            dirty: snapshot.dirty,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::GitSnapshot;
+
+    use super::{Fallback, GitFallbacks};
+
+    #[test]
+    pub fn fill() {
+        let snapshot = GitSnapshot {
+            commit: Some("abc".to_owned()),
+            ..Default::default()
+        };
+        let fallbacks = GitFallbacks {
+            branch: "main".into(),
+            ..GitFallbacks::omit()
+        };
+        let filled = fallbacks.fill(snapshot.clone());
+        assert_eq!(filled.branch.as_deref(), Some("main"));
+        assert_eq!(filled.commit.as_deref(), Some("abc"));
+        assert_eq!(filled.timestamp, None);
+
+        let filled = GitFallbacks::default().fill(GitSnapshot {
+            detached: Some(true),
+            ..snapshot
+        });
+        assert_eq!(filled.branch, None);
+        assert_eq!(filled.message.as_deref(), Some("Unknown"));
+        assert!(filled.timestamp.is_some());
+        assert_eq!(GitFallbacks::omit().commit, Fallback::Omit);
+    }
+}
@@ -0,0 +1,97 @@
+use std::{
+    path::{Path, PathBuf},
+    time::UNIX_EPOCH,
+};
+
+use crate::{discover::DISCOVER_ENV_VARS, DiscoveredRepository};
+
+/// Fingerprints the state of the repository without opening it
+///
+/// Includes the contents of `HEAD`, the commit it resolves to, the modification time of the index,
+/// the `shallow` file and the config and the git environment variables.
+/// Returns `None` if `HEAD` can not be read.
+pub(crate) fn repository_fingerprint(repository: &DiscoveredRepository) -> Option<String> {
+    let git_dir = &repository.git_dir;
+    let head = std::fs::read_to_string(git_dir.join("HEAD")).ok()?;
+    let head = head.trim();
+    let commit = match head.strip_prefix("ref: ") {
+        Some(reference) => resolve_reference(git_dir, reference)
+            .or_else(|| resolve_reference(&repository.common_dir, reference))
+            .unwrap_or_default(),
+        None => head.to_owned(),
+    };
+    let index = modified(&git_dir.join("index"));
+    let shallow = modified(&repository.common_dir.join("shallow"));
+    let config = modified(&repository.common_dir.join("config"));
+    let mut fingerprint = format!(
+        "root={};head={};commit={};index={};shallow={};config={}",
+        repository.root().display(),
+        head,
+        commit,
+        index,
+        shallow,
+        config
+    );
+    for var in DISCOVER_ENV_VARS {
+        if let Ok(value) = std::env::var(var) {
+            fingerprint.push_str(&format!(";{}={}", var, value));
+        }
+    }
+    Some(fingerprint)
+}
+/// The files and directories of the repository the reported values depend on
+///
+/// Used for `cargo:rerun-if-changed` once a `rerun-if` instruction turned off cargo's default of rerunning on every change.
+/// Missing files are left out as cargo would rerun on every build.
+pub(crate) fn repository_watch_paths(repository: &DiscoveredRepository) -> Vec<PathBuf> {
+    let common_dir = &repository.common_dir;
+    [
+        repository.git_dir.join("HEAD"),
+        repository.git_dir.join("index"),
+        common_dir.join("refs"),
+        common_dir.join("packed-refs"),
+        common_dir.join("shallow"),
+        common_dir.join("config"),
+    ]
+    .into_iter()
+    .filter(|path| path.exists())
+    .collect()
+}
+/// The modification time of `path` in nanoseconds. `0` if it does not exist
+fn modified(path: &Path) -> u128 {
+    std::fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+        .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+        .map(|modified| modified.as_nanos())
+        .unwrap_or_default()
+}
+/// Resolves a reference from its loose file or `packed-refs`
+fn resolve_reference(git_dir: &Path, reference: &str) -> Option<String> {
+    if let Ok(commit) = std::fs::read_to_string(git_dir.join(reference)) {
+        return Some(commit.trim().to_owned());
+    }
+    let packed_refs = std::fs::read_to_string(git_dir.join("packed-refs")).ok()?;
+    packed_refs.lines().find_map(|line| {
+        let (commit, name) = line.split_once(' ')?;
+        (name == reference).then(|| commit.to_owned())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Context;
+
+    use crate::discover;
+
+    use super::repository_fingerprint;
+
+    #[test]
+    pub fn test_fingerprint_is_stable() -> anyhow::Result<()> {
+        let repository =
+            discover(&std::env::current_dir()?, true)?.context("Could not find git folder")?;
+        let fingerprint = repository_fingerprint(&repository).context("No fingerprint")?;
+        assert_eq!(Some(fingerprint), repository_fingerprint(&repository));
+        Ok(())
+    }
+}
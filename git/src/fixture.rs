@@ -0,0 +1,209 @@
+//! Throwaway repositories with deterministic commits for the backend tests
+//!
+//! Every command runs with fixed identities and dates and without the global and system git config,
+//! so commit ids are the same on every machine.
+use std::{
+    cell::Cell,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use anyhow::Context;
+use chrono::{DateTime, FixedOffset, TimeZone};
+use tempfile::TempDir;
+
+use crate::{access::LoadGitAccess, GitAuthor};
+
+/// The author of commits created with [GitFixture::commit]
+pub static AUTHOR: (&str, &str) = ("Alice Author", "alice@example.com");
+/// The committer of commits created with [GitFixture::commit]
+pub static COMMITTER: (&str, &str) = ("Bob Committer", "bob@example.com");
+/// The commit date of the first commit. Every following commit is one minute later
+pub static START: i64 = 1_700_000_000;
+
+/// Runs git in `directory` isolated from the configuration of the machine
+pub fn git(directory: &Path, args: &[&str]) -> anyhow::Result<String> {
+    git_with_env(directory, args, &[])
+}
+fn git_with_env(directory: &Path, args: &[&str], env: &[(&str, String)]) -> anyhow::Result<String> {
+    let output = Command::new("git")
+        .args([
+            "-c",
+            "protocol.file.allow=always",
+            "-c",
+            "commit.gpgsign=false",
+        ])
+        .args(["-c", "tag.gpgsign=false", "-c", "init.defaultBranch=main"])
+        .args(args)
+        .current_dir(directory)
+        .env("GIT_CONFIG_GLOBAL", "/dev/null")
+        .env("GIT_CONFIG_NOSYSTEM", "1")
+        .env("GIT_AUTHOR_NAME", AUTHOR.0)
+        .env("GIT_AUTHOR_EMAIL", AUTHOR.1)
+        .env("GIT_COMMITTER_NAME", COMMITTER.0)
+        .env("GIT_COMMITTER_EMAIL", COMMITTER.1)
+        .env("GIT_AUTHOR_DATE", git_date(&utc(START)))
+        .env("GIT_COMMITTER_DATE", git_date(&utc(START)))
+        .envs(env.iter().map(|(key, value)| (key, value)))
+        .env_remove("GIT_DIR")
+        .env_remove("GIT_WORK_TREE")
+        .output()?;
+    anyhow::ensure!(
+        output.status.success(),
+        "git {:?} failed: {}",
+        args,
+        String::from_utf8_lossy(&output.stderr)
+    );
+    Ok(String::from_utf8(output.stdout)?.trim().to_owned())
+}
+fn git_date(time: &DateTime<FixedOffset>) -> String {
+    time.format("%s %z").to_string()
+}
+fn utc(seconds: i64) -> DateTime<FixedOffset> {
+    FixedOffset::east_opt(0)
+        .and_then(|utc| utc.timestamp_opt(seconds, 0).single())
+        .expect("valid timestamp")
+}
+/// A commit created with [GitFixture::commit_with]
+#[derive(Debug, Clone)]
+pub struct FixtureCommit {
+    pub message: String,
+    pub author: GitAuthor,
+    pub committer: GitAuthor,
+    pub author_time: DateTime<FixedOffset>,
+    pub commit_time: DateTime<FixedOffset>,
+    /// Files written and staged before committing
+    pub files: Vec<(String, String)>,
+}
+/// A repository in a temporary directory that is deleted on drop
+pub struct GitFixture {
+    dir: TempDir,
+    commits: Cell<i64>,
+}
+impl GitFixture {
+    /// A repository on the unborn branch `main` without commits
+    pub fn empty() -> anyhow::Result<Self> {
+        let fixture = Self {
+            dir: tempfile::tempdir()?,
+            commits: Cell::new(0),
+        };
+        fixture.git(&["init", "-q", "-b", "main"])?;
+        Ok(fixture)
+    }
+    /// A repository with a single commit `initial` on `main`
+    pub fn new() -> anyhow::Result<Self> {
+        let fixture = Self::empty()?;
+        fixture.commit("initial")?;
+        Ok(fixture)
+    }
+    /// The root of the work tree
+    pub fn path(&self) -> &Path {
+        self.dir.path()
+    }
+    pub fn git(&self, args: &[&str]) -> anyhow::Result<String> {
+        git(self.path(), args)
+    }
+    /// Loads a backend for the repository
+    pub fn load<G: LoadGitAccess>(&self) -> anyhow::Result<G> {
+        let repository =
+            crate::discover(self.path(), false)?.context("Could not find the fixture")?;
+        G::load(&repository)
+    }
+    /// The defaults of the next commit. Its dates are one minute after the previous commit, in UTC+02:00
+    pub fn next_commit(&self, message: &str) -> FixtureCommit {
+        let index = self.commits.get();
+        let time = FixedOffset::east_opt(2 * 3600)
+            .and_then(|offset| offset.timestamp_opt(START + index * 60, 0).single())
+            .expect("valid timestamp");
+        FixtureCommit {
+            message: message.to_owned(),
+            author: GitAuthor::new(AUTHOR.0, AUTHOR.1),
+            committer: GitAuthor::new(COMMITTER.0, COMMITTER.1),
+            author_time: time,
+            commit_time: time,
+            files: Vec::new(),
+        }
+    }
+    /// Commits the staged changes, or nothing, with the defaults of [GitFixture::next_commit]. Returns the commit id
+    pub fn commit(&self, message: &str) -> anyhow::Result<String> {
+        self.commit_with(self.next_commit(message))
+    }
+    pub fn commit_with(&self, commit: FixtureCommit) -> anyhow::Result<String> {
+        self.commits.set(self.commits.get() + 1);
+        for (path, contents) in &commit.files {
+            self.write(path, contents)?;
+            self.git(&["add", "--", path])?;
+        }
+        let env = [
+            ("GIT_AUTHOR_NAME", commit.author.name),
+            ("GIT_AUTHOR_EMAIL", commit.author.email),
+            ("GIT_COMMITTER_NAME", commit.committer.name),
+            ("GIT_COMMITTER_EMAIL", commit.committer.email),
+            ("GIT_AUTHOR_DATE", git_date(&commit.author_time)),
+            ("GIT_COMMITTER_DATE", git_date(&commit.commit_time)),
+        ];
+        git_with_env(
+            self.path(),
+            &[
+                "commit",
+                "-q",
+                "--allow-empty",
+                "--cleanup=verbatim",
+                "-m",
+                &commit.message,
+            ],
+            &env,
+        )?;
+        self.head()
+    }
+    /// The commit `HEAD` points to
+    pub fn head(&self) -> anyhow::Result<String> {
+        self.git(&["rev-parse", "HEAD"])
+    }
+    /// Writes a file into the work tree without staging it
+    pub fn write(&self, path: &str, contents: &str) -> anyhow::Result<PathBuf> {
+        let path = self.path().join(path);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&path, contents)?;
+        Ok(path)
+    }
+    pub fn tag(&self, name: &str) -> anyhow::Result<()> {
+        self.git(&["tag", name]).map(drop)
+    }
+    pub fn annotated_tag(&self, name: &str, message: &str) -> anyhow::Result<()> {
+        self.git(&["tag", "-a", name, "-m", message]).map(drop)
+    }
+    /// Creates `name` at `HEAD` without switching to it
+    pub fn branch(&self, name: &str) -> anyhow::Result<()> {
+        self.git(&["branch", name]).map(drop)
+    }
+    pub fn checkout(&self, name: &str) -> anyhow::Result<()> {
+        self.git(&["checkout", "-q", name]).map(drop)
+    }
+    /// Detaches `HEAD` at `revision`
+    pub fn detach(&self, revision: &str) -> anyhow::Result<()> {
+        self.git(&["checkout", "-q", "--detach", revision])
+            .map(drop)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::GitFixture;
+
+    #[test]
+    pub fn commits_are_reproducible() -> anyhow::Result<()> {
+        let build = || -> anyhow::Result<String> {
+            let fixture = GitFixture::new()?;
+            let mut commit = fixture.next_commit("second");
+            commit
+                .files
+                .push(("src/lib.rs".to_owned(), "fn main() {}\n".to_owned()));
+            fixture.commit_with(commit)
+        };
+        assert_eq!(build()?, build()?);
+        Ok(())
+    }
+}
@@ -1,12 +1,16 @@
-use std::path::PathBuf;
+use crate::{
+    access::{GitAccess, LoadGitAccess},
+    CommitCount, DiscoveredRepository, GitHistory, GitSnapshot, GitSubmodule, GitUpstream,
+    IncompleteHistoryError,
+};
+use std::collections::HashSet;
 
-use crate::GitAcesss;
 use chrono::{DateTime, FixedOffset, TimeZone};
-use gix::{bstr::ByteSlice, Repository};
+use gix::{bstr::ByteSlice, ObjectId, Repository};
 #[derive(thiserror::Error, Debug)]
 pub enum GixError {
     #[error(transparent)]
-    DiscoverError(#[from] gix::discover::Error),
+    OpenError(#[from] gix::open::Error),
     #[error(transparent)]
     TraverseError(#[from] gix::reference::find::existing::Error),
     #[error(transparent)]
@@ -21,108 +25,288 @@ pub enum GixError {
     GixObject(#[from] gix_object::decode::Error),
     #[error(transparent)]
     GitCommitObjectError(#[from] gix::object::commit::Error),
+    #[error(transparent)]
+    SubmodulesError(#[from] gix::submodule::modules::Error),
+    #[error(transparent)]
+    SubmodulePathError(#[from] gix::submodule::config::path::Error),
+    #[error(transparent)]
+    SubmoduleHeadError(#[from] gix::submodule::head_id::Error),
+    #[error(transparent)]
+    SubmoduleOpenError(#[from] gix::submodule::open::Error),
+    #[error(transparent)]
+    HeadIdError(#[from] gix::reference::head_id::Error),
+    #[error(transparent)]
+    ReferencesError(#[from] gix::reference::iter::Error),
+    #[error(transparent)]
+    ReferencesInitError(#[from] gix::reference::iter::init::Error),
+    #[error(transparent)]
+    ReferenceIterError(Box<dyn std::error::Error + Send + Sync + 'static>),
+    #[error(transparent)]
+    ReferencePeelError(#[from] gix::reference::peel::Error),
+    #[error(transparent)]
+    RevParseError(#[from] gix::revision::spec::parse::single::Error),
+    #[error(transparent)]
+    RevWalkError(#[from] gix::revision::walk::Error),
+    #[error(transparent)]
+    RevWalkIterError(#[from] gix::traverse::commit::simple::Error),
+    #[error(transparent)]
+    TrackingRefError(#[from] gix::repository::branch_remote_tracking_ref_name::Error),
+    #[error(transparent)]
+    FindReferenceError(#[from] gix::reference::find::Error),
 }
 pub struct GitoxideAccess {
     repository: Repository,
 }
-impl GitAcesss for GitoxideAccess {
-    type Error = GixError;
-
-    fn load(directory: PathBuf) -> Result<Self, Self::Error>
-    where
-        Self: Sized,
-    {
-        let repository = gix::discover(directory)?;
+impl LoadGitAccess for GitoxideAccess {
+    fn load(repository: &DiscoveredRepository) -> anyhow::Result<Self> {
+        let mut options = gix::open::Options::default();
+        // `git --work-tree` does the same for a work tree given with `GIT_WORK_TREE`
+        if let Some(work_tree) = &repository.work_tree {
+            let mut work_tree_override = gix::bstr::BString::from("core.worktree=");
+            work_tree_override.extend_from_slice(&gix::path::into_bstr(work_tree.as_path()));
+            options = options.cli_overrides([work_tree_override]);
+        }
+        let repository = gix::open_opts(&repository.git_dir, options)?;
         Ok(Self { repository })
     }
-
-    fn get_branch(&self) -> Result<Option<String>, Self::Error> {
+}
+impl GitAccess for GitoxideAccess {
+    fn snapshot(&self) -> anyhow::Result<GitSnapshot> {
         let branch = self
             .repository
             .head_name()?
             .map(|v| v.shorten().to_string());
-        Ok(branch)
-    }
-
-    fn get_commit(&self) -> Result<Option<String>, Self::Error> {
         let mut head = self.repository.head()?;
+        if head.is_unborn() {
+            return Ok(GitSnapshot {
+                branch,
+                detached: Some(false),
+                ..Default::default()
+            });
+        }
+        let detached = head.is_detached();
         let commit = head.peel_to_commit_in_place()?;
-        Ok(Some(commit.id().to_string()))
+        let author = commit.author()?;
+        let author_timestamp = commit_timestamp(author.time);
+        let author = git_author(author)?;
+        let committer = git_author(commit.committer()?)?;
+        let decoded = commit.decode()?;
+        let signed = crate::SIGNATURE_HEADERS
+            .iter()
+            .any(|header| decoded.extra_headers().find(header).is_some());
+        // The first paragraph joined into one line like `git log --format=%s`
+        let message = commit.message()?.summary().to_str()?.to_owned();
+        Ok(GitSnapshot {
+            branch,
+            detached: Some(detached),
+            commit: Some(commit.id().to_string()),
+            commit_short: Some(commit.short_id()?.to_string()),
+            author: Some(author),
+            message: Some(message),
+            full_message: Some(commit.message_raw()?.to_str()?.trim_end().to_owned()),
+            timestamp: commit_timestamp(commit.time()?),
+            committer: Some(committer),
+            author_timestamp,
+            signed: Some(signed),
+            dirty: None,
+        })
     }
 
-    fn get_commit_short(&self) -> Result<Option<String>, Self::Error> {
-        let mut head = self.repository.head()?;
-        let commit = head.peel_to_commit_in_place()?;
-        Ok(Some(commit.short_id()?.to_string()))
+    fn submodules(&self) -> anyhow::Result<Vec<GitSubmodule>> {
+        let Some(submodules) = self.repository.submodules()? else {
+            return Ok(Vec::new());
+        };
+        let mut result = Vec::new();
+        for submodule in submodules {
+            let path = submodule.path()?.to_str()?.to_owned();
+            let recorded_commit = submodule.head_id()?.map(|id| id.to_string());
+            // The repository in `.git/modules` exists even if the work tree has no checkout
+            let commit = match submodule.open()? {
+                Some(repository) if submodule.state()?.worktree_checkout => {
+                    Some(repository.head_id()?.to_string())
+                }
+                _ => None,
+            };
+            result.push(GitSubmodule::new(path, recorded_commit, commit));
+        }
+        Ok(result)
     }
 
-    fn get_commit_author(&self) -> Result<Option<crate::GitAuthor>, Self::Error> {
-        let mut head = self.repository.head()?;
-        let commit = head.peel_to_commit_in_place()?;
-        let author = commit.author()?;
-        Ok(Some(crate::GitAuthor {
-            name: author.name.as_bstr().to_str()?.to_owned(),
-            email: author.email.as_bstr().to_str()?.to_owned(),
+    fn history(&self) -> anyhow::Result<Option<GitHistory>> {
+        let config = self.repository.config_snapshot();
+        let partial = config.string("extensions.partialClone").is_some()
+            || self.repository.remote_names().iter().any(|remote| {
+                config
+                    .boolean(format!("remote.{remote}.promisor").as_str())
+                    .unwrap_or_default()
+            });
+        Ok(Some(GitHistory {
+            shallow: self.repository.is_shallow(),
+            partial,
         }))
     }
 
-    fn get_commit_message(&self) -> Result<Option<String>, Self::Error> {
-        let mut head = self.repository.head()?;
-        let commit = head.peel_to_commit_in_place()?;
-        Ok(Some(
-            commit
-                .message()?
-                .title
-                .as_bstr()
-                .to_str()
-                .map(|s| s.to_string())?,
-        ))
+    fn branches_at_head(&self) -> anyhow::Result<Vec<String>> {
+        let Some(head) = self.head_commit()? else {
+            return Ok(Vec::new());
+        };
+        let references = self.repository.references()?;
+        let mut branches = Vec::new();
+        for reference in references.local_branches()? {
+            let mut reference = reference.map_err(GixError::ReferenceIterError)?;
+            if reference.peel_to_id_in_place()? == head {
+                branches.push(reference.name().shorten().to_str()?.to_owned());
+            }
+        }
+        for reference in references.remote_branches()? {
+            let mut reference = reference.map_err(GixError::ReferenceIterError)?;
+            let name = reference.name().shorten().to_str()?.to_owned();
+            let Some((_, branch)) = name.split_once('/') else {
+                continue;
+            };
+            if branch != "HEAD"
+                && !branches.iter().any(|known| known == branch)
+                && reference.peel_to_id_in_place()? == head
+            {
+                branches.push(branch.to_owned());
+            }
+        }
+        Ok(branches)
     }
 
-    fn get_commit_timestamp(&self) -> Result<Option<DateTime<FixedOffset>>, Self::Error> {
-        let mut head = self.repository.head()?;
-        let commit = head.peel_to_commit_in_place()?;
-        let time = commit.time()?;
-        #[cfg(test)]
-        println!("Time: {:?}", time);
-        let offset = match time.sign {
-            gix::date::time::Sign::Plus => FixedOffset::east_opt(time.offset.abs()),
-            gix::date::time::Sign::Minus => FixedOffset::west_opt(time.offset.abs()),
+    fn get_commit_count(&self, options: &CommitCount) -> anyhow::Result<u64> {
+        if self.repository.is_shallow() {
+            return Err(IncompleteHistoryError {
+                feature: "commit count",
+            }
+            .into());
+        }
+        let hidden = match options.hidden_revision() {
+            Some(revision) => Some(
+                self.repository
+                    .rev_parse_single(revision.as_str())?
+                    .detach(),
+            ),
+            None => None,
+        };
+        let Some(head) = self.head_commit()? else {
+            return Ok(0);
+        };
+        Ok(self.count_commits(head, hidden, options.first_parent)?)
+    }
+
+    fn remote_url(&self, remote: &str) -> anyhow::Result<Option<String>> {
+        let url = self
+            .repository
+            .config_snapshot()
+            .string(format!("remote.{}.url", remote).as_str());
+        Ok(match url {
+            Some(url) => Some(url.to_str()?.to_owned()),
+            None => None,
+        })
+    }
+
+    fn upstream(&self) -> anyhow::Result<Option<GitUpstream>> {
+        let Some(head_name) = self.repository.head_name()? else {
+            return Ok(None);
+        };
+        let Some(tracking_name) = self
+            .repository
+            .branch_remote_tracking_ref_name(head_name.as_ref(), gix::remote::Direction::Fetch)
+        else {
+            return Ok(None);
+        };
+        let tracking_name = tracking_name?;
+        let Some(mut tracking) = self.repository.try_find_reference(tracking_name.as_ref())? else {
+            return Ok(None);
         };
-        let Some(offset) = offset else {
-            #[cfg(test)]
-            println!("Could not get offset");
+        if self.repository.is_shallow() {
+            return Err(IncompleteHistoryError {
+                feature: "ahead and behind counts",
+            }
+            .into());
+        }
+        let upstream = tracking.peel_to_id_in_place()?.detach();
+        let Some(head) = self.head_commit()? else {
             return Ok(None);
         };
-        let datetime = offset
-            .timestamp_millis_opt(time.seconds as i64 * 1000)
-            .single();
-        Ok(datetime)
+        Ok(Some(GitUpstream {
+            name: tracking_name.shorten().to_str()?.to_owned(),
+            ahead: self.count_commits(head, Some(upstream), false)?,
+            behind: self.count_commits(upstream, Some(head), false)?,
+        }))
+    }
+}
+impl GitoxideAccess {
+    /// The commit `HEAD` points to. `None` on an unborn branch
+    #[allow(clippy::result_large_err)]
+    fn head_commit(&self) -> Result<Option<ObjectId>, GixError> {
+        Ok(self.repository.head()?.id().map(|id| id.detach()))
+    }
+    /// Counts the commits reachable from `tip` that are not reachable from `hidden`
+    #[allow(clippy::result_large_err)]
+    fn count_commits(
+        &self,
+        tip: ObjectId,
+        hidden: Option<ObjectId>,
+        first_parent: bool,
+    ) -> Result<u64, GixError> {
+        // The ancestry of the hidden commit is collected first as the walk can only prune by id
+        let mut hidden_commits = HashSet::new();
+        if let Some(hidden) = hidden {
+            for commit in self.repository.rev_walk([hidden]).all()? {
+                hidden_commits.insert(commit?.id);
+            }
+        }
+        let mut walk = self.repository.rev_walk([tip]);
+        if first_parent {
+            walk = walk.first_parent_only();
+        }
+        let mut count = 0;
+        for commit in walk.selected(move |id| !hidden_commits.contains(id))? {
+            commit?;
+            count += 1;
+        }
+        Ok(count)
     }
 }
+fn git_author(
+    signature: gix::actor::SignatureRef<'_>,
+) -> Result<crate::GitAuthor, gix::bstr::Utf8Error> {
+    Ok(crate::GitAuthor {
+        name: signature.name.as_bstr().to_str()?.to_owned(),
+        email: signature.email.as_bstr().to_str()?.to_owned(),
+    })
+}
+fn commit_timestamp(time: gix::date::Time) -> Option<DateTime<FixedOffset>> {
+    #[cfg(test)]
+    println!("Time: {:?}", time);
+    let offset = match time.sign {
+        gix::date::time::Sign::Plus => FixedOffset::east_opt(time.offset.abs()),
+        gix::date::time::Sign::Minus => FixedOffset::west_opt(time.offset.abs()),
+    };
+    let Some(offset) = offset else {
+        #[cfg(test)]
+        println!("Could not get offset");
+        return None;
+    };
+    offset.timestamp_millis_opt(time.seconds * 1000).single()
+}
 
 #[cfg(test)]
-
 mod tests {
-    use anyhow::bail;
-
-    use crate::{find_folder_with_git, GitAcesss};
+    use crate::{access::GitAccess, fixture::GitFixture};
 
     use super::GitoxideAccess;
 
     #[test]
     pub fn test_timestamp() -> anyhow::Result<()> {
-        let git_folder =
-            find_folder_with_git(std::env::current_dir().expect("Could not get current dir"));
-        let Some(git_folder) = git_folder else {
-            bail!("Could not find git folder");
-        };
-        let git_access = GitoxideAccess::load(git_folder)?;
-        let timestamp = git_access.get_commit_timestamp()?;
-        let Some(timestamp) = timestamp else {
-            bail!("Could not get timestamp");
-        };
-        println!("Timestamp: {}", timestamp);
+        let fixture = GitFixture::new()?;
+        let timestamp = fixture.load::<GitoxideAccess>()?.snapshot()?.timestamp;
+        assert_eq!(
+            timestamp.map(|timestamp| timestamp.to_rfc3339()).as_deref(),
+            Some("2023-11-15T00:13:20+02:00")
+        );
         Ok(())
     }
 }
@@ -0,0 +1,161 @@
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+
+/// Which parts of the commit message the git plugin reports
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CommitMessageMode {
+    /// Only the title as `COMMIT_MESSAGE`
+    #[default]
+    Title,
+    /// The title and the complete body, trailers included, as `COMMIT_BODY`
+    Full,
+    /// The title, the body without the trailers and the trailers as `COMMIT_TRAILERS_<KEY>`
+    Trailers,
+}
+/// A commit message split into its parts
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CommitMessage {
+    /// Everything after the title. `None` if the message is only a title
+    pub body: Option<String>,
+    /// The body without the trailer block
+    pub body_without_trailers: Option<String>,
+    /// The trailers in the order they appear. Keys are as written
+    pub trailers: Vec<(String, String)>,
+}
+/// Trailers keyed by their lowercase key. Repeated keys are joined with `, `
+///
+/// `Signed-off-by` becomes `COMMIT_TRAILERS_SIGNED_OFF_BY`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(transparent)]
+pub struct GitTrailers(pub BTreeMap<String, String>);
+
+impl CommitMessage {
+    /// The first paragraph of a raw commit message joined into one line like `git log --format=%s`
+    pub fn title(message: &str) -> Option<String> {
+        let title = message
+            .trim_start()
+            .lines()
+            .map(str::trim)
+            .take_while(|line| !line.is_empty())
+            .collect::<Vec<_>>()
+            .join(" ");
+        Some(title).filter(|title| !title.is_empty())
+    }
+    /// Splits a raw commit message into the body and trailers
+    ///
+    /// The title is the first paragraph. Trailers are read from the last paragraph of the body
+    /// if every line in it is a `Key: value` line or the continuation of one.
+    pub fn parse(message: &str) -> Self {
+        let message = message.replace("\r\n", "\n");
+        let Some((_, body)) = message.trim().split_once("\n\n") else {
+            return Self::default();
+        };
+        let body = body.trim();
+        if body.is_empty() {
+            return Self::default();
+        }
+        let (rest, last_paragraph) = match body.rsplit_once("\n\n") {
+            Some((rest, last)) => (rest.trim_end(), last.trim()),
+            None => ("", body),
+        };
+        let trailers = parse_trailers(last_paragraph).unwrap_or_default();
+        let body_without_trailers = if trailers.is_empty() {
+            Some(body.to_owned())
+        } else {
+            Some(rest.to_owned()).filter(|rest| !rest.is_empty())
+        };
+        Self {
+            body: Some(body.to_owned()),
+            body_without_trailers,
+            trailers,
+        }
+    }
+    pub fn trailer_map(&self) -> GitTrailers {
+        let mut trailers: BTreeMap<String, String> = BTreeMap::new();
+        for (key, value) in &self.trailers {
+            trailers
+                .entry(key.to_lowercase())
+                .and_modify(|values| {
+                    values.push_str(", ");
+                    values.push_str(value);
+                })
+                .or_insert_with(|| value.clone());
+        }
+        GitTrailers(trailers)
+    }
+}
+/// Parses a paragraph where every line is a trailer. Returns `None` otherwise
+fn parse_trailers(paragraph: &str) -> Option<Vec<(String, String)>> {
+    let mut trailers: Vec<(String, String)> = Vec::new();
+    for line in paragraph.lines() {
+        if line.starts_with([' ', '\t']) {
+            let (_, value) = trailers.last_mut()?;
+            value.push(' ');
+            value.push_str(line.trim());
+            continue;
+        }
+        let (key, value) = line.split_once(':')?;
+        if key.is_empty() || !key.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+            return None;
+        }
+        trailers.push((key.to_owned(), value.trim().to_owned()));
+    }
+    Some(trailers)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::CommitMessage;
+
+    #[test]
+    pub fn test_title_only() {
+        assert_eq!(CommitMessage::parse("Fix bug\n"), CommitMessage::default());
+    }
+    #[test]
+    pub fn test_title() {
+        let title = |message| CommitMessage::title(message);
+        assert_eq!(title("Fix bug\n").as_deref(), Some("Fix bug"));
+        assert_eq!(
+            title("Wrapped\ntitle\n\nBody").as_deref(),
+            Some("Wrapped title")
+        );
+        assert_eq!(title("\n"), None);
+    }
+    #[test]
+    pub fn test_trailers() {
+        let message = CommitMessage::parse(
+            "Fix bug\n\nLonger explanation\nof the fix.\n\nSigned-off-by: A <a@example.com>\nCo-authored-by: B <b@example.com>\nChange-Id: I1234\n  5678\nsigned-off-by: C <c@example.com>\n",
+        );
+        assert_eq!(
+            message.body_without_trailers.as_deref(),
+            Some("Longer explanation\nof the fix.")
+        );
+        assert_eq!(message.trailers.len(), 4);
+        assert_eq!(message.trailers[2].1, "I1234 5678");
+        let trailers = message.trailer_map().0;
+        assert_eq!(
+            trailers["signed-off-by"],
+            "A <a@example.com>, C <c@example.com>"
+        );
+        assert_eq!(trailers["co-authored-by"], "B <b@example.com>");
+    }
+    #[test]
+    pub fn test_last_paragraph_is_not_trailers() {
+        let message =
+            CommitMessage::parse("Fix bug\n\nSigned-off-by: A\n\nThis paragraph: is prose\n");
+        assert!(message.trailers.is_empty());
+        assert_eq!(
+            message.body.as_deref(),
+            Some("Signed-off-by: A\n\nThis paragraph: is prose")
+        );
+        assert_eq!(message.body, message.body_without_trailers);
+    }
+    #[test]
+    pub fn test_only_trailers() {
+        let message = CommitMessage::parse("Fix bug\n\nReviewed-by: A\n");
+        assert_eq!(message.body.as_deref(), Some("Reviewed-by: A"));
+        assert_eq!(message.body_without_trailers, None);
+        assert_eq!(message.trailer_map().0["reviewed-by"], "A");
+    }
+}
@@ -1,75 +1,218 @@
-use std::path::PathBuf;
-
 use chrono::{DateTime, FixedOffset, TimeZone};
-use git2::Repository;
+use git2::{BranchType, Repository};
 
-use crate::GitAcesss;
+use crate::{
+    access::{GitAccess, LoadGitAccess},
+    CommitCount, DiscoveredRepository, GitHistory, GitSnapshot, GitSubmodule, GitUpstream,
+    IncompleteHistoryError,
+};
 pub struct NativeGitAccess {
     pub repository: Repository,
 }
 
-impl GitAcesss for NativeGitAccess {
-    type Error = git2::Error;
-    fn load(directory: PathBuf) -> Result<Self, Self::Error>
-    where
-        Self: Sized,
-    {
-        let repository = Repository::open(directory)?;
+impl LoadGitAccess for NativeGitAccess {
+    fn load(repository: &DiscoveredRepository) -> anyhow::Result<Self> {
+        let work_tree = repository.work_tree.as_deref();
+        let repository = Repository::open(&repository.git_dir)?;
+        if let Some(work_tree) = work_tree {
+            repository.set_workdir(work_tree, false)?;
+        }
         Ok(Self { repository })
     }
-    fn get_branch(&self) -> Result<Option<String>, Self::Error> {
-        let head = self.repository.head()?;
-        let branch = head.shorthand();
-        Ok(branch.map(|s| s.to_string()))
+}
+impl GitAccess for NativeGitAccess {
+    fn snapshot(&self) -> anyhow::Result<GitSnapshot> {
+        let Some(head) = self.head()? else {
+            let head = self.repository.find_reference("HEAD")?;
+            let branch = head
+                .symbolic_target()
+                .map(|target| target.trim_start_matches("refs/heads/").to_owned());
+            return Ok(GitSnapshot {
+                branch,
+                detached: Some(false),
+                ..Default::default()
+            });
+        };
+        let detached = self.repository.head_detached()?;
+        let branch = if detached {
+            None
+        } else {
+            head.shorthand().map(|s| s.to_string())
+        };
+        let commit = head.peel_to_commit()?;
+        let author = commit.author();
+        let author_timestamp = commit_timestamp(author.when());
+        let author = git_author(&author);
+        let committer = git_author(&commit.committer());
+        let signed = crate::SIGNATURE_HEADERS
+            .iter()
+            .any(|header| commit.header_field_bytes(header).is_ok());
+        let commit_short = commit
+            .as_object()
+            .short_id()?
+            .as_str()
+            .map(|v| v.to_string());
+        Ok(GitSnapshot {
+            branch,
+            detached: Some(detached),
+            commit: Some(commit.id().to_string()),
+            commit_short,
+            author: Some(author),
+            message: commit.summary().map(|s| s.to_string()),
+            full_message: commit.message().map(|s| s.trim_end().to_string()),
+            timestamp: commit_timestamp(commit.time()),
+            committer: Some(committer),
+            author_timestamp,
+            signed: Some(signed),
+            dirty: None,
+        })
     }
 
-    fn get_commit(&self) -> Result<Option<String>, Self::Error> {
-        let head = self.repository.head()?;
-        let commit = head.peel_to_commit()?;
-        Ok(Some(commit.id().to_string()))
+    fn submodules(&self) -> anyhow::Result<Vec<GitSubmodule>> {
+        let submodules = self.repository.submodules()?;
+        Ok(submodules
+            .iter()
+            .map(|submodule| {
+                GitSubmodule::new(
+                    submodule.path().to_string_lossy().into_owned(),
+                    submodule.head_id().map(|id| id.to_string()),
+                    submodule.workdir_id().map(|id| id.to_string()),
+                )
+            })
+            .collect())
     }
 
-    fn get_commit_author(&self) -> Result<Option<crate::GitAuthor>, Self::Error> {
-        let head = self.repository.head()?;
-        let commit = head.peel_to_commit()?;
-        let author = commit.author();
-        Ok(Some(crate::GitAuthor {
-            name: author.name().map(|s| s.to_string()).unwrap_or_default(),
-            email: author.email().map(|s| s.to_string()).unwrap_or_default(),
+    fn history(&self) -> anyhow::Result<Option<GitHistory>> {
+        let config = self.repository.config()?;
+        let mut partial = config.get_string("extensions.partialclone").is_ok();
+        for remote in self.repository.remotes()?.iter().flatten() {
+            partial |= config
+                .get_bool(&format!("remote.{remote}.promisor"))
+                .unwrap_or_default();
+        }
+        Ok(Some(GitHistory {
+            shallow: self.repository.is_shallow(),
+            partial,
         }))
     }
 
-    fn get_commit_message(&self) -> Result<Option<String>, Self::Error> {
-        let head = self.repository.head()?;
-        let commit = head.peel_to_commit()?;
-        Ok(commit.summary().map(|s| s.to_string()))
+    fn branches_at_head(&self) -> anyhow::Result<Vec<String>> {
+        let Some(head) = self.head()? else {
+            return Ok(Vec::new());
+        };
+        let head = head.peel_to_commit()?.id();
+        let mut branches = Vec::new();
+        for branch_type in [BranchType::Local, BranchType::Remote] {
+            for branch in self.repository.branches(Some(branch_type))? {
+                let (branch, _) = branch?;
+                if branch.get().symbolic_target().is_some()
+                    || branch.get().peel_to_commit()?.id() != head
+                {
+                    continue;
+                }
+                let Some(name) = branch.name()? else {
+                    continue;
+                };
+                let name = match branch_type {
+                    BranchType::Local => name,
+                    BranchType::Remote => match name.split_once('/') {
+                        Some((_, name)) => name,
+                        None => continue,
+                    },
+                };
+                if !branches.iter().any(|known| known == name) {
+                    branches.push(name.to_owned());
+                }
+            }
+        }
+        Ok(branches)
     }
 
-    fn get_commit_timestamp(&self) -> Result<Option<DateTime<FixedOffset>>, Self::Error> {
-        let head = self.repository.head()?;
-        let commit = head.peel_to_commit()?;
-        let time = commit.time();
-        let offset = if time.offset_minutes() >= 0 {
-            FixedOffset::west_opt(time.offset_minutes() * 60)
-        } else {
-            FixedOffset::east_opt(-time.offset_minutes() * 60)
+    fn get_commit_count(&self, options: &CommitCount) -> anyhow::Result<u64> {
+        if self.repository.is_shallow() {
+            let err = IncompleteHistoryError {
+                feature: "commit count",
+            };
+            return Err(err.into());
+        }
+        if self.head()?.is_none() {
+            return Ok(0);
+        }
+        let mut walk = self.repository.revwalk()?;
+        walk.push_head()?;
+        if let Some(revision) = options.hidden_revision() {
+            walk.hide(self.repository.revparse_single(&revision)?.id())?;
+        }
+        if options.first_parent {
+            walk.simplify_first_parent()?;
+        }
+        let mut count = 0;
+        for commit in walk {
+            commit?;
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    fn remote_url(&self, remote: &str) -> anyhow::Result<Option<String>> {
+        match self
+            .repository
+            .config()?
+            .get_string(&format!("remote.{}.url", remote))
+        {
+            Ok(url) => Ok(Some(url)),
+            Err(err) if err.code() == git2::ErrorCode::NotFound => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    fn upstream(&self) -> anyhow::Result<Option<GitUpstream>> {
+        let Some(head) = self.head()? else {
+            return Ok(None);
         };
-        let Some(offset) = offset else {
+        if !head.is_branch() {
             return Ok(None);
+        }
+        let upstream = match git2::Branch::wrap(head).upstream() {
+            Ok(upstream) => upstream,
+            Err(err) if err.code() == git2::ErrorCode::NotFound => return Ok(None),
+            Err(err) => return Err(err.into()),
         };
-        let datetime = offset
-            .timestamp_millis_opt(time.seconds() as i64 * 1000)
-            .single();
-        Ok(datetime)
+        if self.repository.is_shallow() {
+            let err = IncompleteHistoryError {
+                feature: "ahead and behind counts",
+            };
+            return Err(err.into());
+        }
+        let head = self.repository.head()?.peel_to_commit()?.id();
+        let upstream_commit = upstream.get().peel_to_commit()?.id();
+        let (ahead, behind) = self.repository.graph_ahead_behind(head, upstream_commit)?;
+        Ok(Some(GitUpstream {
+            name: upstream.name()?.unwrap_or_default().to_owned(),
+            ahead: ahead as u64,
+            behind: behind as u64,
+        }))
     }
-
-    fn get_commit_short(&self) -> Result<Option<String>, Self::Error> {
-        let head = self.repository.head()?;
-        let commit = head.peel_to_commit()?;
-        Ok(commit
-            .as_object()
-            .short_id()?
-            .as_str()
-            .map(|v| v.to_string()))
+}
+impl NativeGitAccess {
+    /// The reference `HEAD` resolves to. `None` on an unborn branch
+    fn head(&self) -> Result<Option<git2::Reference<'_>>, git2::Error> {
+        match self.repository.head() {
+            Ok(head) => Ok(Some(head)),
+            Err(err) if err.code() == git2::ErrorCode::UnbornBranch => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+}
+fn git_author(signature: &git2::Signature<'_>) -> crate::GitAuthor {
+    crate::GitAuthor {
+        name: signature.name().map(|s| s.to_string()).unwrap_or_default(),
+        email: signature.email().map(|s| s.to_string()).unwrap_or_default(),
     }
 }
+fn commit_timestamp(time: git2::Time) -> Option<DateTime<FixedOffset>> {
+    // `offset_minutes` is positive east of UTC
+    FixedOffset::east_opt(time.offset_minutes() * 60)?
+        .timestamp_millis_opt(time.seconds() * 1000)
+        .single()
+}
@@ -0,0 +1,191 @@
+use chrono::{DateTime, FixedOffset, TimeZone};
+
+use crate::{CommitMessage, GitAuthor, GitSnapshot};
+
+/// The fields that can be set with `{prefix}{FIELD}_OVERRIDE`
+///
+/// `COMMIT_MESSAGE` replaces the whole message, its first paragraph is the title.
+/// Timestamps are RFC 3339 or seconds since the Unix epoch like `SOURCE_DATE_EPOCH`.
+/// `COMMIT_COUNT` and `REMOTE_URL` are only read if `commit_count` and `remote` are set.
+pub static OVERRIDE_FIELDS: &[&str] = &[
+    "BRANCH",
+    "DETACHED",
+    "COMMIT",
+    "COMMIT_SHORT",
+    "COMMIT_AUTHOR_NAME",
+    "COMMIT_AUTHOR_EMAIL",
+    "COMMIT_COMMITTER_NAME",
+    "COMMIT_COMMITTER_EMAIL",
+    "COMMIT_MESSAGE",
+    "COMMIT_TIMESTAMP",
+    "COMMIT_AUTHOR_TIMESTAMP",
+    "COMMIT_SIGNED",
+    "DIRTY",
+    "COMMIT_COUNT",
+    "REMOTE_URL",
+];
+/// The prefix of the override variables if none is configured
+pub static DEFAULT_OVERRIDE_PREFIX: &str = "SMOL_VERGEN_GIT_";
+
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+#[error("Invalid value {value:?} for {var}. Expected {expected}")]
+pub struct OverrideError {
+    pub var: String,
+    pub value: String,
+    pub expected: &'static str,
+}
+/// Reads the override variables of the git plugin
+pub(crate) struct GitOverrides<'a, E> {
+    prefix: &'a str,
+    env: E,
+}
+/// Overrides read through an [EnvLookup]
+pub(crate) type EnvOverrides<'a> = GitOverrides<'a, EnvLookup>;
+/// Reads an environment variable
+pub(crate) type EnvLookup = fn(&str) -> Option<String>;
+/// Reads from the process environment
+pub(crate) fn process_env(var: &str) -> Option<String> {
+    std::env::var(var).ok()
+}
+impl<'a, E: Fn(&str) -> Option<String>> GitOverrides<'a, E> {
+    pub fn new(prefix: &'a str, env: E) -> Self {
+        Self { prefix, env }
+    }
+    /// The name of the override variable of `field`
+    pub fn var(&self, field: &str) -> String {
+        format!("{}{}_OVERRIDE", self.prefix, field)
+    }
+    /// The override of `field`. Empty variables are ignored
+    pub fn get(&self, field: &str) -> Option<String> {
+        (self.env)(&self.var(field)).filter(|value| !value.is_empty())
+    }
+    /// Parses the override of `field`
+    pub fn parse<T>(
+        &self,
+        field: &str,
+        expected: &'static str,
+        parse: impl Fn(&str) -> Option<T>,
+    ) -> Result<Option<T>, OverrideError> {
+        let Some(value) = self.get(field) else {
+            return Ok(None);
+        };
+        match parse(value.trim()) {
+            Some(parsed) => Ok(Some(parsed)),
+            None => Err(OverrideError {
+                var: self.var(field),
+                value,
+                expected,
+            }),
+        }
+    }
+    /// Replaces every field of `snapshot` that has an override
+    pub fn apply(&self, snapshot: &mut GitSnapshot) -> Result<(), OverrideError> {
+        if let Some(branch) = self.get("BRANCH") {
+            snapshot.branch = Some(branch);
+        }
+        if let Some(detached) = self.parse("DETACHED", "true or false", parse_bool)? {
+            snapshot.detached = Some(detached);
+        }
+        if let Some(commit) = self.get("COMMIT") {
+            snapshot.commit_short = commit.get(..7).map(|short| short.to_owned());
+            snapshot.commit = Some(commit);
+        }
+        if let Some(commit_short) = self.get("COMMIT_SHORT") {
+            snapshot.commit_short = Some(commit_short);
+        }
+        self.apply_identity("COMMIT_AUTHOR", &mut snapshot.author);
+        self.apply_identity("COMMIT_COMMITTER", &mut snapshot.committer);
+        if let Some(message) = self.get("COMMIT_MESSAGE") {
+            snapshot.message = CommitMessage::title(&message);
+            snapshot.full_message = Some(message.trim_end().to_owned());
+        }
+        let timestamp = "an RFC 3339 timestamp or seconds since the Unix epoch";
+        if let Some(time) = self.parse("COMMIT_TIMESTAMP", timestamp, parse_timestamp)? {
+            snapshot.timestamp = Some(time);
+        }
+        if let Some(time) = self.parse("COMMIT_AUTHOR_TIMESTAMP", timestamp, parse_timestamp)? {
+            snapshot.author_timestamp = Some(time);
+        }
+        if let Some(signed) = self.parse("COMMIT_SIGNED", "true or false", parse_bool)? {
+            snapshot.signed = Some(signed);
+        }
+        if let Some(dirty) = self.parse("DIRTY", "true or false", parse_bool)? {
+            snapshot.dirty = Some(dirty);
+        }
+        Ok(())
+    }
+    /// Overrides the name and email separately, keeping the other from the repository
+    fn apply_identity(&self, field: &str, identity: &mut Option<GitAuthor>) {
+        let name = self.get(&format!("{}_NAME", field));
+        let email = self.get(&format!("{}_EMAIL", field));
+        if name.is_none() && email.is_none() {
+            return;
+        }
+        let identity = identity.get_or_insert_with(|| GitAuthor::new("", ""));
+        if let Some(name) = name {
+            identity.name = name;
+        }
+        if let Some(email) = email {
+            identity.email = email;
+        }
+    }
+}
+fn parse_bool(value: &str) -> Option<bool> {
+    match value {
+        "true" | "1" => Some(true),
+        "false" | "0" => Some(false),
+        _ => None,
+    }
+}
+fn parse_timestamp(value: &str) -> Option<DateTime<FixedOffset>> {
+    if let Ok(seconds) = value.parse::<i64>() {
+        return FixedOffset::east_opt(0)?.timestamp_opt(seconds, 0).single();
+    }
+    DateTime::parse_from_rfc3339(value).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::GitSnapshot;
+
+    use super::GitOverrides;
+
+    #[test]
+    pub fn apply_overrides() {
+        let env: HashMap<&str, &str> = [
+            (
+                "NIX_COMMIT_OVERRIDE",
+                "531f9554534c6b2cc10851ac97c564222a14a888",
+            ),
+            ("NIX_COMMIT_AUTHOR_NAME_OVERRIDE", "Jane Doe"),
+            ("NIX_COMMIT_MESSAGE_OVERRIDE", "Title\n\nBody\n"),
+            ("NIX_COMMIT_TIMESTAMP_OVERRIDE", "1714613400"),
+            ("NIX_BRANCH_OVERRIDE", ""),
+        ]
+        .into_iter()
+        .collect();
+        let overrides = GitOverrides::new("NIX_", |var| env.get(var).map(|v| v.to_string()));
+        let mut snapshot = GitSnapshot {
+            branch: Some("main".to_owned()),
+            ..Default::default()
+        };
+        overrides.apply(&mut snapshot).unwrap();
+        assert_eq!(snapshot.branch.as_deref(), Some("main"));
+        assert_eq!(snapshot.commit_short.as_deref(), Some("531f955"));
+        assert_eq!(snapshot.author.as_ref().unwrap().name, "Jane Doe");
+        assert_eq!(snapshot.message.as_deref(), Some("Title"));
+        assert_eq!(snapshot.full_message.as_deref(), Some("Title\n\nBody"));
+        assert_eq!(
+            snapshot.timestamp.unwrap().to_rfc3339(),
+            "2024-05-02T01:30:00+00:00"
+        );
+
+        let overrides = GitOverrides::new("NIX_", |var| {
+            (var == "NIX_DIRTY_OVERRIDE").then(|| "maybe".to_owned())
+        });
+        let err = overrides.apply(&mut snapshot).unwrap_err();
+        assert_eq!(err.var, "NIX_DIRTY_OVERRIDE");
+    }
+}
@@ -0,0 +1,112 @@
+use std::path::Path;
+
+use serde::Deserialize;
+
+use crate::{access::GitAccess, CommitCount, GitHistory, GitSnapshot, GitSubmodule, GitUpstream};
+
+/// The file cargo writes into the root of a packaged crate
+pub(crate) static VCS_INFO_FILE: &str = ".cargo_vcs_info.json";
+
+#[derive(thiserror::Error, Debug)]
+pub enum VcsInfoError {
+    #[error("Failed to read {VCS_INFO_FILE}: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Invalid {VCS_INFO_FILE}: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("{0} is not available in a packaged crate")]
+    Unsupported(&'static str),
+}
+/// The contents of `.cargo_vcs_info.json`
+///
+/// `{"git": {"sha1": "531f955…", "dirty": true}, "path_in_vcs": "git"}`. `dirty` is only written if it is true.
+#[derive(Debug, Clone, Deserialize)]
+struct CargoVcsInfo {
+    git: CargoVcsGitInfo,
+}
+#[derive(Debug, Clone, Deserialize)]
+struct CargoVcsGitInfo {
+    sha1: String,
+    #[serde(default)]
+    dirty: bool,
+}
+/// Reads the commit of a crate built from a `.crate` file or a vendored copy
+///
+/// Only the commit and the dirty flag survive packaging.
+pub struct VcsInfoAccess {
+    info: CargoVcsInfo,
+}
+impl VcsInfoAccess {
+    /// Reads `.cargo_vcs_info.json` in `directory`. `None` if it does not exist
+    pub(crate) fn read(directory: &Path) -> Result<Option<Self>, VcsInfoError> {
+        let content = match std::fs::read_to_string(directory.join(VCS_INFO_FILE)) {
+            Ok(content) => content,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(err.into()),
+        };
+        Ok(Some(Self {
+            info: serde_json::from_str(&content)?,
+        }))
+    }
+}
+impl GitAccess for VcsInfoAccess {
+    fn snapshot(&self) -> anyhow::Result<GitSnapshot> {
+        let commit = &self.info.git.sha1;
+        Ok(GitSnapshot {
+            commit: Some(commit.clone()),
+            commit_short: commit.get(..7).map(|short| short.to_owned()),
+            dirty: Some(self.info.git.dirty),
+            ..Default::default()
+        })
+    }
+
+    fn submodules(&self) -> anyhow::Result<Vec<GitSubmodule>> {
+        Ok(Vec::new())
+    }
+
+    fn history(&self) -> anyhow::Result<Option<GitHistory>> {
+        Ok(None)
+    }
+
+    fn branches_at_head(&self) -> anyhow::Result<Vec<String>> {
+        Ok(Vec::new())
+    }
+
+    fn get_commit_count(&self, _: &CommitCount) -> anyhow::Result<u64> {
+        Err(VcsInfoError::Unsupported("The commit count").into())
+    }
+
+    fn remote_url(&self, _: &str) -> anyhow::Result<Option<String>> {
+        Ok(None)
+    }
+
+    fn upstream(&self) -> anyhow::Result<Option<GitUpstream>> {
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::access::GitAccess;
+
+    use super::{VcsInfoAccess, VCS_INFO_FILE};
+
+    #[test]
+    pub fn read_vcs_info() -> anyhow::Result<()> {
+        let directory = tempfile::tempdir()?;
+        assert!(VcsInfoAccess::read(directory.path())?.is_none());
+        std::fs::write(
+            directory.path().join(VCS_INFO_FILE),
+            r#"{"git": {"sha1": "531f9554534c6b2cc10851ac97c564222a14a888", "dirty": true}, "path_in_vcs": "git"}"#,
+        )?;
+        let access = VcsInfoAccess::read(directory.path())?.expect("vcs info exists");
+        let snapshot = access.snapshot()?;
+        assert_eq!(
+            snapshot.commit.as_deref(),
+            Some("531f9554534c6b2cc10851ac97c564222a14a888")
+        );
+        assert_eq!(snapshot.commit_short.as_deref(), Some("531f955"));
+        assert_eq!(snapshot.dirty, Some(true));
+        assert_eq!(snapshot.branch, None);
+        Ok(())
+    }
+}
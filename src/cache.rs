@@ -0,0 +1,271 @@
+use std::{collections::BTreeMap, path::Path};
+
+use chrono::{DateTime, FixedOffset};
+use serde::{Deserialize, Serialize};
+use smol_vergen_core::{
+    output::{emit, Directive},
+    warn, SmolVergenContext, Value,
+};
+
+/// The name of the cache file when it is stored in `OUT_DIR`
+pub(crate) static CACHE_FILE_NAME: &str = "smol-vergen-cache.json";
+
+/// Items produced by previous plugin runs keyed by the plugin id and the fingerprint of the plugin
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub(crate) struct Cache {
+    entries: BTreeMap<String, BTreeMap<String, CacheEntry>>,
+}
+/// The items a single plugin run added to the context and the directives it emitted
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub(crate) struct CacheEntry {
+    plugins: BTreeMap<String, CachedPluginItems>,
+    directives: Vec<Directive>,
+}
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct CachedPluginItems {
+    items: BTreeMap<String, CachedValue>,
+    complex_items: BTreeMap<String, serde_json::Value>,
+}
+/// A [Value] stored with its variant
+///
+/// [Value] deserializes any string that parses as RFC 3339 into a timestamp.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", content = "value")]
+enum CachedValue {
+    String(String),
+    Bool(bool),
+    Float(f64),
+    Number(i64),
+    Char(char),
+    DateTime(DateTime<FixedOffset>),
+}
+impl From<&Value> for CachedValue {
+    fn from(value: &Value) -> Self {
+        match value {
+            Value::String(value) => CachedValue::String(value.clone()),
+            Value::Bool(value) => CachedValue::Bool(*value),
+            Value::Float(value) => CachedValue::Float(*value),
+            Value::Number(value) => CachedValue::Number(*value),
+            Value::Char(value) => CachedValue::Char(*value),
+            Value::DateTime(value) => CachedValue::DateTime(*value),
+        }
+    }
+}
+impl From<CachedValue> for Value {
+    fn from(value: CachedValue) -> Self {
+        match value {
+            CachedValue::String(value) => Value::String(value),
+            CachedValue::Bool(value) => Value::Bool(value),
+            CachedValue::Float(value) => Value::Float(value),
+            CachedValue::Number(value) => Value::Number(value),
+            CachedValue::Char(value) => Value::Char(value),
+            CachedValue::DateTime(value) => Value::DateTime(value),
+        }
+    }
+}
+impl Cache {
+    /// Loads the cache. A missing or unreadable cache is treated as empty
+    pub fn load(path: &Path) -> Self {
+        let Ok(content) = std::fs::read_to_string(path) else {
+            return Self::default();
+        };
+        match serde_json::from_str(&content) {
+            Ok(cache) => cache,
+            Err(err) => {
+                warn!("Ignoring invalid cache {}: {}", path.display(), err);
+                Self::default()
+            }
+        }
+    }
+    pub fn save(&self, path: &Path) -> anyhow::Result<()> {
+        let content = serde_json::to_string(self)?;
+        std::fs::write(path, content)?;
+        Ok(())
+    }
+    pub fn get(&self, plugin_id: &str, fingerprint: &str) -> Option<&CacheEntry> {
+        self.entries.get(plugin_id)?.get(fingerprint)
+    }
+    pub fn insert(&mut self, plugin_id: &str, fingerprint: String, entry: CacheEntry) {
+        self.entries
+            .entry(plugin_id.to_owned())
+            .or_default()
+            .insert(fingerprint, entry);
+    }
+}
+impl CacheEntry {
+    /// Captures all items of the context and the directives emitted while it was filled
+    pub fn capture(
+        context: &SmolVergenContext,
+        directives: Vec<Directive>,
+    ) -> anyhow::Result<Self> {
+        let mut plugins = BTreeMap::new();
+        for (plugin_id, plugin_items) in context.iter() {
+            let mut cached = CachedPluginItems::default();
+            for (key, value) in &plugin_items.items {
+                cached.items.insert(key.clone(), value.into());
+            }
+            for (key, value) in &plugin_items.complex_items {
+                let mut value = serde_json::to_value(value.as_ref())?;
+                // `None` fields are skipped when flattened but a `null` would fail to serialize
+                if let serde_json::Value::Object(map) = &mut value {
+                    map.retain(|_, v| !v.is_null());
+                }
+                cached.complex_items.insert(key.clone(), value);
+            }
+            plugins.insert(plugin_id.to_owned(), cached);
+        }
+        Ok(Self {
+            plugins,
+            directives,
+        })
+    }
+    /// Adds the cached items to the context and emits the cached directives again
+    ///
+    /// Cargo forgets `rerun-if` instructions that are not emitted by the latest run.
+    pub fn restore(&self, context: &mut SmolVergenContext) -> anyhow::Result<()> {
+        for directive in &self.directives {
+            emit(directive.clone());
+        }
+        for (plugin_id, cached) in &self.plugins {
+            let plugin_items = context.get_plugin_items(plugin_id);
+            for (key, value) in &cached.items {
+                plugin_items.add_item(key, Value::from(value.clone()));
+            }
+            for (key, value) in &cached.complex_items {
+                plugin_items.add_complex_item(key, value.clone())?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    use smol_vergen_core::{
+        output::{emit, Directive},
+        Plugin, SmolVergenContext, TimestampPolicy, UnloadedPlugin,
+    };
+
+    use crate::SmolVergenBuilder;
+
+    #[derive(serde::Serialize)]
+    struct Author {
+        name: String,
+        email: Option<String>,
+    }
+    #[derive(Clone)]
+    struct CountingPlugin {
+        id: &'static str,
+        runs: Arc<AtomicUsize>,
+        fingerprint: &'static str,
+    }
+    impl Plugin for CountingPlugin {
+        fn id(&self) -> &'static str {
+            self.id
+        }
+        fn run(&mut self, context: &mut SmolVergenContext) -> anyhow::Result<()> {
+            self.runs.fetch_add(1, Ordering::SeqCst);
+            emit(Directive::RerunIfEnvChanged("COUNTING_INPUT".to_owned()));
+            let items = context.get_plugin_items(self.id);
+            items.add_item("FINGERPRINT", self.fingerprint);
+            items.add_item("SUBJECT", "2024-05-01T23:30:00-02:00");
+            items.add_item(
+                "TIMESTAMP",
+                chrono::DateTime::parse_from_rfc3339("2024-05-01T23:30:00-02:00")?,
+            );
+            items.add_complex_item(
+                "AUTHOR",
+                Author {
+                    name: "John Doe".to_owned(),
+                    email: None,
+                },
+            )?;
+            Ok(())
+        }
+        fn fingerprint(&self) -> Option<String> {
+            Some(self.fingerprint.to_owned())
+        }
+    }
+    impl UnloadedPlugin for CountingPlugin {
+        fn load(&self, _: std::path::PathBuf) -> anyhow::Result<Box<dyn Plugin>> {
+            Ok(Box::new(self.clone()))
+        }
+    }
+
+    #[test]
+    pub fn cached_plugin_is_not_run_again() -> anyhow::Result<()> {
+        let cache_file = std::env::temp_dir().join(format!(
+            "smol-vergen-cache-test-{}.json",
+            std::process::id()
+        ));
+        let runs = Arc::new(AtomicUsize::new(0));
+        let run = |fingerprint| -> anyhow::Result<_> {
+            SmolVergenBuilder {
+                directory: Some(std::env::temp_dir()),
+                ..Default::default()
+            }
+            .add_plugin(CountingPlugin {
+                id: "COUNTING",
+                runs: runs.clone(),
+                fingerprint,
+            })
+            .cache_file(&cache_file)
+            .timestamp_policy(TimestampPolicy::all())
+            .build()?
+            .run_capture()
+        };
+        let first = run("a")?;
+        let second = run("a")?;
+        assert_eq!(runs.load(Ordering::SeqCst), 1);
+        assert_eq!(first, second);
+        second.assert_value("SMOL_VERGEN_COUNTING_AUTHOR_NAME", "John Doe");
+        // Strings that look like timestamps are not turned into timestamps by the cache
+        second.assert_value("SMOL_VERGEN_COUNTING_SUBJECT", "2024-05-01T23:30:00-02:00");
+        second.assert_missing("SMOL_VERGEN_COUNTING_SUBJECT_UNIX");
+        second.assert_key("SMOL_VERGEN_COUNTING_TIMESTAMP_UNIX");
+        assert_eq!(second.rerun_if_env_changed(), vec!["COUNTING_INPUT"]);
+
+        let third = run("b")?;
+        assert_eq!(runs.load(Ordering::SeqCst), 2);
+        third.assert_value("SMOL_VERGEN_COUNTING_FINGERPRINT", "b");
+        std::fs::remove_file(&cache_file)?;
+        Ok(())
+    }
+    #[test]
+    pub fn plugins_with_the_same_fingerprint_keep_their_items() -> anyhow::Result<()> {
+        let cache_file = std::env::temp_dir().join(format!(
+            "smol-vergen-cache-shared-test-{}.json",
+            std::process::id()
+        ));
+        let runs = Arc::new(AtomicUsize::new(0));
+        let run = || -> anyhow::Result<_> {
+            let plugin = |id| CountingPlugin {
+                id,
+                runs: runs.clone(),
+                fingerprint: "a",
+            };
+            SmolVergenBuilder {
+                directory: Some(std::env::temp_dir()),
+                ..Default::default()
+            }
+            .add_plugin(plugin("FIRST"))
+            .add_plugin(plugin("SECOND"))
+            .cache_file(&cache_file)
+            .build()?
+            .run_capture()
+        };
+        let first = run()?;
+        let second = run()?;
+        assert_eq!(runs.load(Ordering::SeqCst), 2);
+        assert_eq!(first, second);
+        second.assert_value("SMOL_VERGEN_FIRST_FINGERPRINT", "a");
+        second.assert_value("SMOL_VERGEN_SECOND_FINGERPRINT", "a");
+        std::fs::remove_file(&cache_file)?;
+        Ok(())
+    }
+}
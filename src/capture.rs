@@ -0,0 +1,70 @@
+//! Runs the plugins and returns their output instead of printing it
+use std::collections::BTreeMap;
+
+use smol_vergen_core::output::capture;
+pub use smol_vergen_core::output::Directive;
+
+use crate::SmolVergenBuilder;
+
+/// Everything a run wrote to the output of the build script, in order
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CapturedOutput {
+    pub directives: Vec<Directive>,
+}
+impl CapturedOutput {
+    /// The `cargo:rustc-env` values. Later values replace earlier ones as they do in cargo
+    pub fn env(&self) -> BTreeMap<String, String> {
+        self.directives
+            .iter()
+            .filter_map(|directive| match directive {
+                Directive::RustcEnv { key, value } => Some((key.clone(), value.clone())),
+                _ => None,
+            })
+            .collect()
+    }
+    /// The value written to `key`
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.directives
+            .iter()
+            .rev()
+            .find_map(|directive| match directive {
+                Directive::RustcEnv { key: name, value } if name == key => Some(value.as_str()),
+                _ => None,
+            })
+    }
+    pub fn warnings(&self) -> Vec<&str> {
+        self.directives
+            .iter()
+            .filter_map(|directive| match directive {
+                Directive::Warning(message) => Some(message.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+    /// The lines that are not cargo instructions
+    pub fn logs(&self) -> Vec<&str> {
+        self.directives
+            .iter()
+            .filter_map(|directive| match directive {
+                Directive::Log(line) => Some(line.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+    /// The variables passed to `cargo:rerun-if-env-changed`
+    pub fn rerun_if_env_changed(&self) -> Vec<&str> {
+        self.directives
+            .iter()
+            .filter_map(|directive| match directive {
+                Directive::RerunIfEnvChanged(var) => Some(var.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+}
+/// Loads and runs the plugins of `builder` and captures the output of both
+pub fn run(builder: SmolVergenBuilder) -> anyhow::Result<CapturedOutput> {
+    let (result, directives) = capture(|| builder.build()?.run_on_env());
+    result?;
+    Ok(CapturedOutput { directives })
+}
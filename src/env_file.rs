@@ -0,0 +1,145 @@
+use std::{collections::BTreeMap, io::Write};
+
+use thiserror::Error;
+
+/// A file the values are written to in addition to `cargo:rustc-env`
+///
+/// Complex items are written flattened, as they are passed to cargo. Line breaks are kept as line breaks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EnvFileFormat {
+    /// `KEY=value` lines as read by `docker compose`, `dotenvy` and `python-dotenv`
+    ///
+    /// Values with spaces or shell characters are single quoted so they are not interpolated.
+    /// Values containing a single quote or a line break are double quoted with `\\`, `\"`, `\$`, `\n` and `\r` escaped.
+    Dotenv,
+    /// `export KEY='value'` lines for POSIX `sh`. Source it with `. ./file`
+    ShellExport,
+}
+#[derive(Debug, Error)]
+pub enum EnvFileError {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error("{0:?} is not a valid environment variable name for an env file")]
+    InvalidName(String),
+}
+impl EnvFileFormat {
+    pub fn write(
+        self,
+        values: &BTreeMap<String, String>,
+        out: &mut impl Write,
+    ) -> Result<(), EnvFileError> {
+        writeln!(out, "# Generated by smol-vergen")?;
+        for (key, value) in values {
+            if !is_valid_name(key) {
+                return Err(EnvFileError::InvalidName(key.clone()));
+            }
+            match self {
+                EnvFileFormat::Dotenv => writeln!(out, "{}={}", key, dotenv_quote(value))?,
+                EnvFileFormat::ShellExport => {
+                    writeln!(out, "export {}={}", key, shell_quote(value))?
+                }
+            }
+        }
+        Ok(())
+    }
+}
+/// Names must be valid shell identifiers so they can be exported
+fn is_valid_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+fn dotenv_quote(value: &str) -> String {
+    let plain = |c: char| c.is_ascii_alphanumeric() || "_-.,:/@+%".contains(c);
+    if value.chars().all(plain) {
+        return value.to_owned();
+    }
+    if !value.contains(['\'', '\n', '\r']) {
+        return format!("'{}'", value);
+    }
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+    for c in value.chars() {
+        match c {
+            '\\' => quoted.push_str("\\\\"),
+            '"' => quoted.push_str("\\\""),
+            '$' => quoted.push_str("\\$"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+/// Single quotes the value. A single quote ends the quoting, is escaped and quoting starts again
+fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', r"'\''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::{EnvFileError, EnvFileFormat};
+
+    static VALUES: &[(&str, &str)] = &[
+        ("PLAIN", "v1.2.3-rc.1+build"),
+        ("SPACES", "Fix $HOME \"parsing\""),
+        ("QUOTE", "It's"),
+        ("LINES", "Title\n\nBody with \\ and $PATH"),
+        ("EMPTY", ""),
+    ];
+    fn write(format: EnvFileFormat) -> String {
+        let values: BTreeMap<String, String> = VALUES
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect();
+        let mut out = Vec::new();
+        format.write(&values, &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+    #[test]
+    pub fn dotenv_quoting() {
+        assert_eq!(
+            write(EnvFileFormat::Dotenv),
+            "# Generated by smol-vergen\n\
+             EMPTY=\n\
+             LINES=\"Title\\n\\nBody with \\\\ and \\$PATH\"\n\
+             PLAIN=v1.2.3-rc.1+build\n\
+             QUOTE=\"It's\"\n\
+             SPACES='Fix $HOME \"parsing\"'\n"
+        );
+    }
+    /// Sources the script with `sh` and reads every value back
+    #[cfg(unix)]
+    #[test]
+    pub fn shell_export_round_trip() -> anyhow::Result<()> {
+        let mut script = write(EnvFileFormat::ShellExport);
+        for (key, _) in VALUES {
+            script.push_str(&format!("printf '%s\\0' \"${}\"\n", key));
+        }
+        let output = std::process::Command::new("sh")
+            .arg("-c")
+            .arg(&script)
+            .env_clear()
+            .output()?;
+        assert!(output.status.success());
+        let values: Vec<&str> = std::str::from_utf8(&output.stdout)?
+            .split_terminator('\0')
+            .collect();
+        let expected: Vec<&str> = VALUES.iter().map(|(_, value)| *value).collect();
+        assert_eq!(values, expected);
+        Ok(())
+    }
+    #[test]
+    pub fn invalid_names() {
+        let values = BTreeMap::from([("SMOL_VERGEN_GIT_SUBMODULES_A-B".to_owned(), String::new())]);
+        let err = EnvFileFormat::ShellExport
+            .write(&values, &mut Vec::new())
+            .unwrap_err();
+        assert!(matches!(err, EnvFileError::InvalidName(_)));
+    }
+}
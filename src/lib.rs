@@ -1,16 +1,98 @@
-#![recursion_limit = "256"]
 use ahash::{HashMap, HashMapExt};
 use anyhow::Context;
-use std::path::PathBuf;
+use cache::{Cache, CacheEntry};
+use heck::ToShoutySnakeCase;
+use std::{collections::BTreeMap, fmt::Display, path::PathBuf};
+use thiserror::Error;
+mod cache;
+pub mod capture;
+mod env_file;
+pub mod testing;
 
-use serialize_to_env::SerializeToEnv;
-use smol_vergen_core::{Plugin, SmolVergenContext, SmolVergenPluginItems, UnloadedPlugin};
-mod serialize_to_env;
+use capture::CapturedOutput;
+pub use env_file::{EnvFileError, EnvFileFormat};
+use smol_vergen_core::{
+    output::{capture, emit, OutputSink},
+    warn, Plugin, SmolVergenContext, UnloadedPlugin,
+};
+pub use smol_vergen_core::{
+    serialize_to_env::SerializeToEnvError, ComplexItemError, TimestampFormat, TimestampPolicy,
+};
+
+/// The prefix of every environment variable unless [SmolVergenBuilder::prefix] is set
+pub static DEFAULT_PREFIX: &str = "SMOL_VERGEN";
+
+/// What to do when two items resolve to the same environment variable
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum KeyCollisionPolicy {
+    /// Fail with a [KeyCollisionError]
+    #[default]
+    Error,
+    /// Emit a cargo warning and keep the value resolved last
+    Warn,
+}
+/// The plugin item an environment variable was produced from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ItemSource {
+    pub plugin: String,
+    pub key: String,
+    pub complex: bool,
+}
+impl Display for ItemSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.complex {
+            write!(f, "complex item {}.{}", self.plugin, self.key)
+        } else {
+            write!(f, "item {}.{}", self.plugin, self.key)
+        }
+    }
+}
+/// Two items resolved to the same environment variable
+///
+/// Keys are compared after converting them to SHOUTY_SNAKE_CASE
+#[derive(Debug, Error)]
+#[error("{key} is set by both {first} and {second}")]
+pub struct KeyCollisionError {
+    pub key: String,
+    pub first: ItemSource,
+    pub second: ItemSource,
+}
+/// The [Plugin::dependencies] of the plugins can not be satisfied
+#[derive(Debug, Error)]
+pub enum DependencyError {
+    #[error("Plugin {plugin} depends on {dependency} but no plugin with that id is registered")]
+    Unknown { plugin: String, dependency: String },
+    #[error("Plugins {0:?} depend on each other")]
+    Cycle(Vec<String>),
+}
+/// A plugin panicked while running on its own thread
+#[derive(Debug, Error)]
+#[error("Plugin {plugin} panicked: {message}")]
+pub struct PluginPanicError {
+    pub plugin: String,
+    pub message: String,
+}
 
 #[derive(Default)]
 pub struct SmolVergenBuilder {
     pub plugins: Vec<Box<dyn UnloadedPlugin>>,
     pub directory: Option<PathBuf>,
+    /// Serialize complex items when they are added instead of when they are saved
+    pub validate_complex_items: bool,
+    /// What to do when two items resolve to the same environment variable
+    pub key_collision_policy: KeyCollisionPolicy,
+    /// Where to cache the output of plugins between runs
+    pub cache_file: Option<PathBuf>,
+    /// Run plugins one after another instead of concurrently
+    pub sequential: bool,
+    /// How timestamps are written to the environment
+    pub timestamp_policy: TimestampPolicy,
+    /// Timestamp policies for single environment variables. Keyed by the full variable name
+    pub key_timestamp_policies: HashMap<String, TimestampPolicy>,
+    /// The prefix of every environment variable. [DEFAULT_PREFIX] if `None`
+    pub prefix: Option<String>,
+    /// Files the values are written to in addition to `cargo:rustc-env`
+    pub env_files: Vec<(EnvFileFormat, PathBuf)>,
 }
 
 impl SmolVergenBuilder {
@@ -19,6 +101,63 @@ impl SmolVergenBuilder {
         self.plugins.push(plugin);
         self
     }
+    /// Rather or not to validate complex items when they are added by a plugin
+    pub fn validate_complex_items(mut self, validate: bool) -> Self {
+        self.validate_complex_items = validate;
+        self
+    }
+    /// What to do when two items resolve to the same environment variable
+    pub fn key_collision_policy(mut self, policy: KeyCollisionPolicy) -> Self {
+        self.key_collision_policy = policy;
+        self
+    }
+    /// Cache the output of plugins in `OUT_DIR`
+    ///
+    /// Plugins providing a [Plugin::fingerprint] are not run again until their fingerprint changes.
+    pub fn cache(mut self, enabled: bool) -> Self {
+        self.cache_file = if enabled {
+            std::env::var_os("OUT_DIR")
+                .map(|out_dir| PathBuf::from(out_dir).join(cache::CACHE_FILE_NAME))
+        } else {
+            None
+        };
+        self
+    }
+    /// Rather or not to run plugins one after another instead of concurrently
+    pub fn sequential(mut self, sequential: bool) -> Self {
+        self.sequential = sequential;
+        self
+    }
+    /// How timestamps are written to the environment
+    pub fn timestamp_policy(mut self, policy: TimestampPolicy) -> Self {
+        self.timestamp_policy = policy;
+        self
+    }
+    /// How the timestamp written to `key` is written. For example `SMOL_VERGEN_GIT_COMMIT_TIMESTAMP`
+    ///
+    /// Overrides [SmolVergenBuilder::timestamp_policy] for this variable.
+    pub fn key_timestamp_policy(mut self, key: impl Into<String>, policy: TimestampPolicy) -> Self {
+        self.key_timestamp_policies.insert(key.into(), policy);
+        self
+    }
+    /// The prefix of every environment variable. Variables are named `{prefix}_{PLUGIN}_{KEY}`
+    pub fn prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.prefix = Some(prefix.into());
+        self
+    }
+    /// Also write the values to `path`, for Dockerfiles and release scripts that need the same values as the binary
+    ///
+    /// Relative paths are relative to the working directory, the crate root in build scripts.
+    /// Build scripts should write to `OUT_DIR` instead, as `cargo publish` rejects changes to the source tree.
+    pub fn env_file(mut self, format: EnvFileFormat, path: impl Into<PathBuf>) -> Self {
+        self.env_files.push((format, path.into()));
+        self
+    }
+    /// Cache the output of plugins in the given file
+    pub fn cache_file(mut self, cache_file: impl Into<PathBuf>) -> Self {
+        self.cache_file = Some(cache_file.into());
+        self
+    }
 
     pub fn build(self) -> anyhow::Result<SmolVergen> {
         let directory = self
@@ -31,9 +170,16 @@ impl SmolVergenBuilder {
             .map(|v| v.load(directory.clone()))
             .collect::<anyhow::Result<Vec<Box<dyn Plugin>>>>()?;
         Ok(SmolVergen {
-            plugins: plugins,
-            directory: directory,
-            context: SmolVergenContext::default(),
+            plugins,
+            directory,
+            context: SmolVergenContext::new(self.validate_complex_items),
+            key_collision_policy: self.key_collision_policy,
+            cache_file: self.cache_file,
+            sequential: self.sequential,
+            timestamp_policy: self.timestamp_policy,
+            key_timestamp_policies: self.key_timestamp_policies,
+            prefix: self.prefix.unwrap_or_else(|| DEFAULT_PREFIX.to_owned()),
+            env_files: self.env_files,
         })
     }
 }
@@ -43,46 +189,527 @@ pub struct SmolVergen {
     #[allow(dead_code)]
     directory: PathBuf,
     pub context: SmolVergenContext,
+    key_collision_policy: KeyCollisionPolicy,
+    cache_file: Option<PathBuf>,
+    sequential: bool,
+    timestamp_policy: TimestampPolicy,
+    key_timestamp_policies: HashMap<String, TimestampPolicy>,
+    prefix: String,
+    env_files: Vec<(EnvFileFormat, PathBuf)>,
 }
 
 impl SmolVergen {
     pub fn run_on_env(&mut self) -> SmolVergenResult {
-        for plugin in &mut self.plugins {
-            plugin.run(&mut self.context)?;
-        }
+        self.run_plugins()?;
         self.save_to_env()?;
         Ok(())
     }
+    /// Runs all plugins like [SmolVergen::run_on_env] but returns the output instead of printing it
+    pub fn run_capture(&mut self) -> anyhow::Result<CapturedOutput> {
+        let (result, directives) = capture(|| self.run_on_env());
+        result?;
+        Ok(CapturedOutput { directives })
+    }
+    /// Runs all plugins and merges their items into the context in plugin order
+    ///
+    /// Plugins with dependencies run afterwards, each after the plugins it depends on.
+    fn run_plugins(&mut self) -> anyhow::Result<()> {
+        let dependent_order = dependent_order(&self.plugins)?;
+        let previous = self.cache_file.as_deref().map(Cache::load);
+        let independent = self
+            .plugins
+            .iter_mut()
+            .filter(|plugin| plugin.dependencies().is_empty());
+        let outputs: Vec<anyhow::Result<PluginOutput>> = if self.sequential {
+            independent
+                .map(|plugin| run_plugin(plugin.as_mut(), self.context.fork(), previous.as_ref()))
+                .collect()
+        } else {
+            // Output of the plugin threads goes to the sink of the caller when capturing
+            let sink = OutputSink::current();
+            std::thread::scope(|scope| {
+                let handles: Vec<_> = independent
+                    .map(|plugin| {
+                        let context = self.context.fork();
+                        let previous = previous.as_ref();
+                        let sink = sink.clone();
+                        let id = plugin.id();
+                        let handle = scope.spawn(move || {
+                            let _guard = sink.as_ref().map(OutputSink::install);
+                            run_plugin(plugin.as_mut(), context, previous)
+                        });
+                        (id, handle)
+                    })
+                    .collect();
+                handles
+                    .into_iter()
+                    .map(|(id, handle)| {
+                        handle.join().unwrap_or_else(|payload| {
+                            Err(PluginPanicError {
+                                plugin: id.to_owned(),
+                                message: panic_message(payload.as_ref()),
+                            }
+                            .into())
+                        })
+                    })
+                    .collect()
+            })
+        };
+        let mut cache = Cache::default();
+        for output in outputs {
+            let (context, entry) = output?;
+            self.context.merge(context);
+            if let Some((plugin_id, fingerprint, entry)) = entry {
+                cache.insert(plugin_id, fingerprint, entry);
+            }
+        }
+        for index in dependent_order {
+            self.plugins[index].run(&mut self.context)?;
+        }
+        match &self.cache_file {
+            Some(cache_file) => cache.save(cache_file),
+            None => Ok(()),
+        }
+    }
+
+    /// Resolves every item of every plugin into its environment variable
+    ///
+    /// Plugins and keys are visited in sorted order so collisions are reported deterministically.
+    pub fn env_values(&self) -> anyhow::Result<BTreeMap<String, String>> {
+        let mut values = BTreeMap::new();
+        let mut sources: HashMap<String, (String, ItemSource)> = HashMap::new();
+        let mut plugins: Vec<_> = self.context.iter().collect();
+        plugins.sort_by_key(|(plugin_id, _)| *plugin_id);
+        for (plugin_id, plugin_items) in plugins {
+            let base_name = format!("{}_{}", self.prefix, plugin_id);
+            let mut resolved = Vec::new();
+            for key in sorted_keys(&plugin_items.items) {
+                let source = ItemSource {
+                    plugin: plugin_id.to_owned(),
+                    key: key.clone(),
+                    complex: false,
+                };
+                let env_key = format!("{}_{}", base_name, key);
+                let policy = self
+                    .key_timestamp_policies
+                    .get(&env_key)
+                    .unwrap_or(&self.timestamp_policy);
+                for (env_key, value) in plugin_items.items[key].to_env_values(&env_key, policy) {
+                    resolved.push((env_key, value, source.clone()));
+                }
+            }
+            for key in sorted_keys(&plugin_items.complex_items) {
+                let map = plugin_items.complex_item_to_map(&base_name, key)?;
+                let mut map: Vec<_> = map.into_iter().collect();
+                map.sort();
+                for (env_key, value) in map {
+                    let source = ItemSource {
+                        plugin: plugin_id.to_owned(),
+                        key: key.clone(),
+                        complex: true,
+                    };
+                    resolved.push((env_key, value, source));
+                }
+            }
+            for (env_key, value, source) in resolved {
+                if let Some((first_key, first)) = sources.insert(
+                    env_key.to_shouty_snake_case(),
+                    (env_key.clone(), source.clone()),
+                ) {
+                    let error = KeyCollisionError {
+                        key: env_key.clone(),
+                        first,
+                        second: source,
+                    };
+                    match self.key_collision_policy {
+                        KeyCollisionPolicy::Error => return Err(error.into()),
+                        KeyCollisionPolicy::Warn => warn!("{}", error),
+                    }
+                    values.remove(&first_key);
+                }
+                values.insert(env_key, value);
+            }
+        }
+        Ok(values)
+    }
+    pub(crate) fn save_to_env(&self) -> anyhow::Result<()> {
+        let values = self.env_values()?;
+        for (key, value) in &values {
+            smol_vergen_core::add_to_env(key, value);
+        }
+        for (format, path) in &self.env_files {
+            let mut contents = Vec::new();
+            format.write(&values, &mut contents)?;
+            std::fs::write(path, contents)
+                .with_context(|| format!("Failed to write {}", path.display()))?;
+        }
+        Ok(())
+    }
+}
+/// The items a plugin produced and the plugin id and fingerprint to cache them under
+type PluginOutput = (
+    SmolVergenContext,
+    Option<(&'static str, String, CacheEntry)>,
+);
+/// Runs a plugin on its own context or restores its items from the cache
+fn run_plugin(
+    plugin: &mut dyn Plugin,
+    mut context: SmolVergenContext,
+    previous: Option<&Cache>,
+) -> anyhow::Result<PluginOutput> {
+    let Some(previous) = previous else {
+        plugin.run(&mut context)?;
+        return Ok((context, None));
+    };
+    let Some(fingerprint) = plugin.fingerprint() else {
+        plugin.run(&mut context)?;
+        return Ok((context, None));
+    };
+    let entry = if let Some(entry) = previous.get(plugin.id(), &fingerprint) {
+        entry.restore(&mut context)?;
+        entry.clone()
+    } else {
+        // The directives are recorded so a cached run can emit them again
+        let (result, directives) = capture(|| plugin.run(&mut context));
+        for directive in &directives {
+            emit(directive.clone());
+        }
+        result?;
+        CacheEntry::capture(&context, directives)?
+    };
+    Ok((context, Some((plugin.id(), fingerprint, entry))))
+}
+/// The indices of the plugins with dependencies in an order that runs every plugin after its dependencies
+///
+/// Plugins that do not depend on each other keep their registration order.
+fn dependent_order(plugins: &[Box<dyn Plugin>]) -> Result<Vec<usize>, DependencyError> {
+    for plugin in plugins {
+        for dependency in plugin.dependencies() {
+            if !plugins.iter().any(|other| other.id() == *dependency) {
+                return Err(DependencyError::Unknown {
+                    plugin: plugin.id().to_owned(),
+                    dependency: dependency.to_string(),
+                });
+            }
+        }
+    }
+    let mut pending: Vec<usize> = (0..plugins.len())
+        .filter(|index| !plugins[*index].dependencies().is_empty())
+        .collect();
+    let mut order = Vec::with_capacity(pending.len());
+    while !pending.is_empty() {
+        let ready = pending.iter().position(|index| {
+            plugins[*index].dependencies().iter().all(|dependency| {
+                pending
+                    .iter()
+                    .all(|other| plugins[*other].id() != *dependency)
+            })
+        });
+        let Some(ready) = ready else {
+            let ids = pending
+                .iter()
+                .map(|index| plugins[*index].id().to_owned())
+                .collect();
+            return Err(DependencyError::Cycle(ids));
+        };
+        order.push(pending.remove(ready));
+    }
+    Ok(order)
+}
+/// The message a plugin panicked with
+fn panic_message(payload: &(dyn std::any::Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        (*message).to_owned()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "Box<dyn Any>".to_owned()
+    }
+}
+fn sorted_keys<V>(map: &HashMap<String, V>) -> Vec<&String> {
+    let mut keys: Vec<_> = map.keys().collect();
+    keys.sort();
+    keys
+}
+
+#[cfg(test)]
+mod tests {
+    use smol_vergen_core::{Plugin, SmolVergenContext, UnloadedPlugin, Value};
 
-    pub(crate) fn save_plugin_to_env(
-        &self,
-        base_name: &str,
-        plugin_items: &SmolVergenPluginItems,
-    ) -> anyhow::Result<()> {
-        for (key, value) in &plugin_items.items {
-            let key = format!("{}_{}", base_name, key);
-            value.add_to_env(&key);
-        }
-        let mut map = HashMap::new();
-        for (key, value) in &plugin_items.complex_items {
-            let prefix = format!("{}_{}", base_name, key);
+    use crate::{
+        DependencyError, KeyCollisionError, KeyCollisionPolicy, PluginPanicError, SmolVergen,
+        SmolVergenBuilder, TimestampFormat, TimestampPolicy,
+    };
 
-            let mut ser = SerializeToEnv {
-                prefix,
-                result: &mut map,
-            };
-            erased_serde::serialize(value.as_ref(), &mut ser).unwrap();
+    fn smol_vergen(policy: KeyCollisionPolicy) -> SmolVergen {
+        SmolVergenBuilder {
+            directory: Some(std::env::temp_dir()),
+            ..Default::default()
         }
-        for (key, value) in map {
-            smol_vergen_core::add_to_env(&key, &value);
+        .key_collision_policy(policy)
+        .build()
+        .unwrap()
+    }
+    #[derive(serde::Serialize)]
+    struct Author {
+        name: String,
+    }
+    #[test]
+    pub fn item_collides_with_complex_item() {
+        let mut smol_vergen = smol_vergen(KeyCollisionPolicy::Error);
+        let items = smol_vergen.context.get_plugin_items("GIT");
+        items.add_item("COMMIT_AUTHOR_NAME", "John Doe");
+        items
+            .add_complex_item(
+                "COMMIT_AUTHOR",
+                Author {
+                    name: "Jane Doe".to_owned(),
+                },
+            )
+            .unwrap();
+        let err = smol_vergen.env_values().unwrap_err();
+        let err = err.downcast::<KeyCollisionError>().unwrap();
+        assert_eq!(err.key, "SMOL_VERGEN_GIT_COMMIT_AUTHOR_NAME");
+        assert_eq!(err.first.key, "COMMIT_AUTHOR_NAME");
+        assert_eq!(err.second.key, "COMMIT_AUTHOR");
+    }
+    #[test]
+    pub fn case_folded_collision_warns() {
+        let mut smol_vergen = smol_vergen(KeyCollisionPolicy::Warn);
+        let items = smol_vergen.context.get_plugin_items("GIT");
+        items.add_item("commitShort", "abc");
+        items.add_item("COMMIT_SHORT", "def");
+        let values = smol_vergen.env_values().unwrap();
+        assert_eq!(values.len(), 1);
+        assert_eq!(values["SMOL_VERGEN_GIT_commitShort"], "abc");
+    }
+    #[test]
+    pub fn timestamp_policies() -> anyhow::Result<()> {
+        let mut smol_vergen = SmolVergenBuilder {
+            directory: Some(std::env::temp_dir()),
+            ..Default::default()
         }
+        .timestamp_policy(TimestampPolicy::new(TimestampFormat::Unix))
+        .key_timestamp_policy("SMOL_VERGEN_GIT_COMMIT_TIMESTAMP", TimestampPolicy::all())
+        .build()?;
+        let timestamp = chrono::DateTime::parse_from_rfc3339("2024-05-01T23:30:00-02:00")?;
+        let items = smol_vergen.context.get_plugin_items("GIT");
+        items.add_item("COMMIT_TIMESTAMP", timestamp);
+        items.add_item("COMMIT_AUTHOR_TIMESTAMP", timestamp);
+        let values = smol_vergen.env_values()?;
+        assert_eq!(
+            values["SMOL_VERGEN_GIT_COMMIT_AUTHOR_TIMESTAMP"],
+            "1714613400"
+        );
+        assert_eq!(
+            values["SMOL_VERGEN_GIT_COMMIT_TIMESTAMP"],
+            "2024-05-01T23:30:00-02:00"
+        );
+        assert_eq!(
+            values["SMOL_VERGEN_GIT_COMMIT_TIMESTAMP_UNIX"],
+            "1714613400"
+        );
+        assert_eq!(values["SMOL_VERGEN_GIT_COMMIT_DATE"], "2024-05-01");
+        assert_eq!(values.len(), 4);
         Ok(())
     }
-    pub(crate) fn save_to_env(&self) -> anyhow::Result<()> {
-        for (plugin_id, items) in self.context.iter() {
-            let base_name = format!("SMOL_VERGEN_{}", plugin_id);
-            self.save_plugin_to_env(&base_name, items)?;
+
+    struct WarnPlugin;
+    impl Plugin for WarnPlugin {
+        fn id(&self) -> &'static str {
+            "WARN"
+        }
+        fn run(&mut self, context: &mut SmolVergenContext) -> anyhow::Result<()> {
+            smol_vergen_core::warn!("from a plugin thread");
+            context
+                .get_plugin_items("WARN")
+                .add_item("VALUE", "multi\nline");
+            Ok(())
+        }
+    }
+    impl UnloadedPlugin for WarnPlugin {
+        fn load(&self, _: std::path::PathBuf) -> anyhow::Result<Box<dyn Plugin>> {
+            Ok(Box::new(WarnPlugin))
+        }
+    }
+    #[test]
+    pub fn run_capture() -> anyhow::Result<()> {
+        let output = crate::testing::run_plugin(WarnPlugin, std::env::temp_dir())?;
+        assert_eq!(output.warnings(), vec!["from a plugin thread"]);
+        output.assert_value("SMOL_VERGEN_WARN_VALUE", "multi\nline");
+        output.assert_missing("SMOL_VERGEN_WARN_OTHER");
+        Ok(())
+    }
+    #[test]
+    pub fn env_files() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        SmolVergenBuilder {
+            directory: Some(std::env::temp_dir()),
+            ..Default::default()
+        }
+        .add_plugin(WarnPlugin)
+        .env_file(crate::EnvFileFormat::Dotenv, dir.path().join("build.env"))
+        .env_file(
+            crate::EnvFileFormat::ShellExport,
+            dir.path().join("build.sh"),
+        )
+        .build()?
+        .run_capture()?;
+        let dotenv = std::fs::read_to_string(dir.path().join("build.env"))?;
+        assert!(dotenv.ends_with("\nSMOL_VERGEN_WARN_VALUE=\"multi\\nline\"\n"));
+        let script = std::fs::read_to_string(dir.path().join("build.sh"))?;
+        assert!(script.ends_with("\nexport SMOL_VERGEN_WARN_VALUE='multi\nline'\n"));
+        Ok(())
+    }
+    #[test]
+    pub fn custom_prefix() -> anyhow::Result<()> {
+        let output = SmolVergenBuilder {
+            directory: Some(std::env::temp_dir()),
+            ..Default::default()
+        }
+        .prefix("APP")
+        .add_plugin(WarnPlugin)
+        .build()?
+        .run_capture()?;
+        output.assert_value("APP_WARN_VALUE", "multi\nline");
+        output.assert_missing("SMOL_VERGEN_WARN_VALUE");
+        Ok(())
+    }
+
+    #[derive(Clone)]
+    struct OrderPlugin {
+        id: &'static str,
+        value: &'static str,
+        dependencies: &'static [&'static str],
+    }
+    impl OrderPlugin {
+        fn independent(value: &'static str) -> Self {
+            Self {
+                id: "ORDER",
+                value,
+                dependencies: &[],
+            }
+        }
+        fn dependent(id: &'static str, dependencies: &'static [&'static str]) -> Self {
+            Self {
+                id,
+                value: "",
+                dependencies,
+            }
+        }
+    }
+    impl Plugin for OrderPlugin {
+        fn id(&self) -> &'static str {
+            self.id
+        }
+        fn run(&mut self, context: &mut SmolVergenContext) -> anyhow::Result<()> {
+            if self.dependencies.is_empty() {
+                std::thread::sleep(std::time::Duration::from_millis(
+                    self.value.len() as u64 * 10,
+                ));
+                context
+                    .get_plugin_items(self.id)
+                    .add_item("VALUE", self.value);
+            } else {
+                // Copies the value of the first dependency
+                let value = context
+                    .get_plugin_items(self.dependencies[0])
+                    .items
+                    .get("VALUE")
+                    .cloned()
+                    .unwrap_or_else(|| Value::from("missing"));
+                context.get_plugin_items(self.id).add_item("VALUE", value);
+            }
+            Ok(())
+        }
+        fn dependencies(&self) -> &[&'static str] {
+            self.dependencies
+        }
+    }
+    impl UnloadedPlugin for OrderPlugin {
+        fn load(&self, _: std::path::PathBuf) -> anyhow::Result<Box<dyn Plugin>> {
+            Ok(Box::new(self.clone()))
+        }
+    }
+    fn with_plugins(plugins: Vec<OrderPlugin>) -> anyhow::Result<SmolVergen> {
+        let mut builder = SmolVergenBuilder {
+            directory: Some(std::env::temp_dir()),
+            ..Default::default()
+        };
+        for plugin in plugins {
+            builder = builder.add_plugin(plugin);
         }
+        builder.build()
+    }
+    #[test]
+    pub fn plugins_merge_in_plugin_order() -> anyhow::Result<()> {
+        let mut smol_vergen = with_plugins(vec![
+            OrderPlugin::dependent("SECOND", &["FIRST"]),
+            OrderPlugin::dependent("FIRST", &["ORDER"]),
+            OrderPlugin::independent("slow first"),
+            OrderPlugin::independent("last"),
+        ])?;
+        smol_vergen.run_plugins()?;
+        let last = Value::from("last");
+        for id in ["ORDER", "FIRST", "SECOND"] {
+            assert_eq!(
+                smol_vergen.context.get_plugin_items(id).items["VALUE"],
+                last
+            );
+        }
+        Ok(())
+    }
+    #[test]
+    pub fn invalid_dependencies() -> anyhow::Result<()> {
+        let err = with_plugins(vec![OrderPlugin::dependent("FIRST", &["MISSING"])])?
+            .run_plugins()
+            .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<DependencyError>(),
+            Some(DependencyError::Unknown { plugin, dependency }) if plugin == "FIRST" && dependency == "MISSING"
+        ));
+        let err = with_plugins(vec![
+            OrderPlugin::dependent("FIRST", &["SECOND"]),
+            OrderPlugin::dependent("SECOND", &["FIRST"]),
+            OrderPlugin::independent("value"),
+        ])?
+        .run_plugins()
+        .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<DependencyError>(),
+            Some(DependencyError::Cycle(ids)) if ids == &["FIRST", "SECOND"]
+        ));
         Ok(())
     }
+
+    struct PanicPlugin;
+    impl Plugin for PanicPlugin {
+        fn id(&self) -> &'static str {
+            "PANIC"
+        }
+        fn run(&mut self, _: &mut SmolVergenContext) -> anyhow::Result<()> {
+            panic!("failed after {} tries", 3);
+        }
+    }
+    impl UnloadedPlugin for PanicPlugin {
+        fn load(&self, _: std::path::PathBuf) -> anyhow::Result<Box<dyn Plugin>> {
+            Ok(Box::new(PanicPlugin))
+        }
+    }
+    #[test]
+    pub fn panic_names_plugin() {
+        let err = SmolVergenBuilder {
+            directory: Some(std::env::temp_dir()),
+            ..Default::default()
+        }
+        .add_plugin(PanicPlugin)
+        .add_plugin(WarnPlugin)
+        .build()
+        .unwrap()
+        .run_capture()
+        .unwrap_err();
+        let err = err.downcast::<PluginPanicError>().unwrap();
+        assert_eq!(err.plugin, "PANIC");
+        assert_eq!(err.message, "failed after 3 tries");
+    }
 }
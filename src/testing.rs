@@ -0,0 +1,54 @@
+//! Helpers to test plugins and build scripts without writing to stdout
+use std::path::PathBuf;
+
+pub use smol_vergen_core::output::{capture, Directive, OutputSink};
+use smol_vergen_core::UnloadedPlugin;
+
+pub use crate::capture::{run, CapturedOutput};
+use crate::SmolVergenBuilder;
+
+impl CapturedOutput {
+    /// The value written to `key`. Panics with the written keys if it is missing
+    #[track_caller]
+    pub fn assert_key(&self, key: &str) -> &str {
+        match self.get(key) {
+            Some(value) => value,
+            None => panic!(
+                "{} was not written. Written keys: {:?}",
+                key,
+                self.env().keys().collect::<Vec<_>>()
+            ),
+        }
+    }
+    #[track_caller]
+    pub fn assert_value(&self, key: &str, expected: &str) {
+        assert_eq!(
+            self.assert_key(key),
+            expected,
+            "Unexpected value of {}",
+            key
+        );
+    }
+    #[track_caller]
+    pub fn assert_missing(&self, key: &str) {
+        if let Some(value) = self.get(key) {
+            panic!("{} was written with {:?}", key, value);
+        }
+    }
+    #[track_caller]
+    pub fn assert_no_warnings(&self) {
+        let warnings = self.warnings();
+        assert!(warnings.is_empty(), "Unexpected warnings: {:?}", warnings);
+    }
+}
+/// Loads and runs `plugin` in `directory` and captures the output of both
+pub fn run_plugin<T: UnloadedPlugin + 'static>(
+    plugin: T,
+    directory: impl Into<PathBuf>,
+) -> anyhow::Result<CapturedOutput> {
+    run(SmolVergenBuilder {
+        directory: Some(directory.into()),
+        ..Default::default()
+    }
+    .add_plugin(plugin))
+}